@@ -1,25 +1,694 @@
-mod core;
-
 use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use signal_hook::consts::SIGINT;
+
+use organizer::core::{
+    ConflictPolicy, FileOperationMode, FileOrganizerCore, FileOutcome, OrganizeOptions, OrganizePlan, OrganizerError,
+    OrganizerResult, PlannedOperation, SkippedFile,
+};
+use organizer::core::{
+    count_by_ignore_file, format_size, parse_size, DateBound, IgnoredPath, ScanOptions, TypeMismatch, UnreadablePath,
+};
+use organizer::core::{
+    CategoryStrategy, DateGrouping, DateStrategy, NamePattern, NamePatternStrategy, PathTemplate, RuleSet,
+    StrategyRegistry, TypeDetection,
+};
+use organizer::core::{DuplicateAction, DuplicateReport, KeepRule};
+use organizer::core::{DuplicatesDocument, ReportFormat, RunEvent, RunReport};
 
-use core::{FileOrganizerCore, FileOperationMode};
+/// Todo se hizo (o no había nada que hacer)
+const EXIT_SUCCESS: i32 = 0;
+/// La ejecución no llegó a empezar o se detuvo: argumentos, ruta o diario no válidos
+const EXIT_FATAL: i32 = 1;
+/// La ejecución terminó, pero algún archivo falló
+const EXIT_PARTIAL: i32 = 2;
+/// Se detuvo con Ctrl-C (128 + SIGINT, como en los shells)
+const EXIT_CANCELLED: i32 = 130;
+
+/// Formato de lo que se escribe en stdout
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum OutputFormat {
+    #[default]
+    Text,
+    /// Un único documento al terminar
+    Json,
+    /// Un evento por línea según se procesa cada archivo
+    Ndjson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            _ => Err(format!("Unknown output format '{}' (text, json, ndjson)", s)),
+        }
+    }
+}
+
+/// Valor de `--output-format` en `args[*i + 1]`; sale con `EXIT_FATAL` si no es válido
+fn parse_output_format(args: &[String], i: &mut usize) -> OutputFormat {
+    *i += 1;
+    let value = args.get(*i).map(String::as_str).unwrap_or_default();
+    match value.parse() {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            print_usage();
+            process::exit(EXIT_FATAL);
+        }
+    }
+}
+
+/// Informa de un error que detiene la ejecución y sale con `EXIT_FATAL`
+fn fail(error: &OrganizerError, format: OutputFormat) -> ! {
+    eprintln!("Error: {}", error);
+    if format != OutputFormat::Text {
+        println!("{}", RunEvent::Error(error).to_line());
+    }
+    process::exit(EXIT_FATAL);
+}
 
 fn print_usage() {
     println!("Usage: organizer-cli <path> [OPTIONS]");
+    println!("       organizer-cli undo <path> [<run-id>] [--output <dir>] [--output-format <FORMAT>]");
+    println!("       organizer-cli dupes <path> [OPTIONS]");
     println!();
-    println!("Organize files by extension in the specified path");
+    println!("Organize files by extension (or by category, date, size or name) in the specified path");
     println!();
     println!("Arguments:");
     println!("  <path>              Path to the directory to organize");
+    println!("  <run-id>            Run to revert with `undo` (defaults to the latest one)");
+    println!();
+    println!("Commands:");
+    println!("  undo                Revert a previous run");
+    println!("  dupes               List files with identical content (scans all subfolders unless --depth");
+    println!("                      is given; accepts the --exclude/--include and filter options)");
+    println!();
+    println!("Duplicate options:");
+    println!("      --action <ACTION>");
+    println!("                      What to do with the extra copies: quarantine (move to <output>/Duplicates),");
+    println!("                      delete, hardlink, symlink; can be reverted with `undo`");
+    println!("      --keep <RULE>   Copy kept in each group: shortest (path, default), oldest, newest");
+    println!("      --keep-in <DIR> Keep the copy inside DIR (relative to <path>); falls back to the shortest path");
+    println!("  -n, --dry-run       Show what --action would do without touching any file");
+    println!("  -o, --output <DIR>  Folder that holds Duplicates/ and the journal (default: <path>/Organizer)");
     println!();
     println!("Options:");
     println!("  -c, --copy          Copy files instead of moving them");
+    println!("  -o, --output <DIR>  Destination folder (default: <path>/Organizer); can be outside <path>");
+    println!("  -n, --dry-run       Show the organization plan without touching any file");
+    println!("      --stream        Start moving files while the folder is still being scanned, for");
+    println!("                      very large trees (no plan first; totals are estimates until the scan ends)");
+    println!("  -d, --depth <N>     Subfolder levels to scan: 0 = top level only (default), N levels, all");
+    println!("  -x, --exclude <PATTERN>");
+    println!("                      Skip paths matching a gitignore-style glob (`*.part`, `node_modules/`,");
+    println!("                      `docs/keep.pdf`, `!keep.part`) or a regex (`re:^tmp_`); can be repeated");
+    println!("  -i, --include <PATTERN>");
+    println!("                      Only organize files matching one of these patterns; can be repeated");
+    println!("      --no-ignore     Do not read .organizerignore files");
+    println!("      --symlinks <POLICY>");
+    println!("                      What to do with symbolic links: skip (default, leave them in place),");
+    println!("                      link (move the link itself) or follow (scan linked folders, once each)");
+    println!("      --min-size <SIZE>");
+    println!("                      Only organize files at least this big (500, 64K, 10M, 1.5G)");
+    println!("      --max-size <SIZE>");
+    println!("                      Only organize files at most this big");
+    println!("      --modified-before <DATE>");
+    println!("                      Only organize files modified before DATE (YYYY-MM-DD, or 7d = 7 days ago)");
+    println!("      --modified-after <DATE>");
+    println!("                      Only organize files modified on or after DATE");
+    println!("      --name <GLOB>   Only organize files whose name matches GLOB (`*`, `?`); can be repeated");
+    println!("      --skip-hidden   Leave hidden files and folders alone");
+    println!("      --match-any     Organize files that meet any size/date/name filter instead of all");
+    println!("      --verify        Verify every copy (and cross-disk move) with a content hash");
+    println!("  -j, --jobs <N>      Transfer up to N files at once (default 1)");
+    println!("  -s, --strategy <NAME>");
+    println!("                      Grouping strategy: extension (default), category, date, size, pattern");
+    println!("      --categories    Group files into the built-in categories (Imagenes, Documentos...)");
+    println!("      --rules <FILE>  Group files with the categories defined in a TOML or JSON file");
+    println!("      --detect        Read file headers to classify files without an extension");
+    println!("      --prefer-content");
+    println!("                      Like --detect, but the detected type wins when it disagrees with the extension");
+    println!("      --by-date       Group files into date folders (default template: {{year}}/{{month}})");
+    println!("      --date-source <SOURCE>");
+    println!("                      Date used with --by-date: modified (default), created, taken (EXIF)");
+    println!("      --date-template <TEMPLATE>");
+    println!("                      Folder template with {{year}}, {{month}} and {{day}}");
+    println!("      --by-size       Group files into size buckets (Pequenos, Medianos, Grandes, Enormes)");
+    println!("      --by-pattern <PATTERN=FOLDER>");
+    println!("                      Send files whose name matches PATTERN (`*`, `?`) to FOLDER; can be repeated,");
+    println!("                      files matching no pattern are left in place");
+    println!("      --template <TEMPLATE>");
+    println!("                      Destination path (folders and file name) inside Organizer/, using");
+    println!("                      {{ext}} {{EXT}} {{name}} {{stem}} {{category}} {{year}} {{month}} {{day}}");
+    println!("                      {{size_bucket}} {{parent}} {{folder}} {{hash8}} {{hash}}");
+    println!("      --on-conflict <POLICY>");
+    println!("                      What to do when a destination already exists:");
+    println!("                      skip, overwrite, rename (default), prefix, newest, largest, ask");
+    println!("      --output-format <FORMAT>");
+    println!("                      What to print on stdout: text (default), json (one document at the end),");
+    println!("                      ndjson (one event per file as it is processed, then a summary);");
+    println!("                      `undo` and `dupes` accept it too");
+    println!("      --report <FILE> Write a report of the run to FILE (ignored with --dry-run)");
+    println!("      --report-format <FORMAT>");
+    println!("                      Report format: json, csv (one row per file), md, html");
+    println!("                      (default: from the FILE extension, else json)");
     println!("  -h, --help          Print help information");
     println!();
     println!("Examples:");
     println!("  organizer-cli /home/user/Downloads");
     println!("  organizer-cli /home/user/Downloads --copy");
+    println!("  organizer-cli /home/user/Downloads --dry-run");
+    println!("  organizer-cli /home/user/Downloads --depth all");
+    println!("  organizer-cli /home/user/Projects --depth all --exclude 'node_modules/' --exclude '*.part'");
+    println!("  organizer-cli /home/user/Downloads --modified-before 7d --min-size 10M --match-any");
+    println!("  organizer-cli /home/user/Downloads --on-conflict newest");
+    println!("  organizer-cli /home/user/Downloads --rules rules.toml");
+    println!("  organizer-cli /home/user/Pictures --by-date --date-source taken");
+    println!("  organizer-cli /home/user/Pictures --by-pattern 'IMG_*=Camara' --by-pattern 'Screenshot*=Capturas'");
+    println!("  organizer-cli /home/user/Downloads --template '{{category}}/{{year}}/{{stem}}_{{hash8}}.{{ext}}'");
+    println!("  organizer-cli /home/user/Downloads --output /mnt/backup/Downloads");
+    println!("  organizer-cli /home/user/Photos --copy --output /mnt/nas/Photos --jobs 4");
+    println!("  organizer-cli /home/user/Downloads --report run.csv");
+    println!("  organizer-cli undo /home/user/Downloads");
+    println!("  organizer-cli dupes /home/user/Downloads --min-size 1M");
+    println!("  organizer-cli dupes /home/user/Downloads --keep oldest --action hardlink --dry-run");
+    println!("  organizer-cli /home/user/Downloads --output-format ndjson | jq -c 'select(.status == \"failed\")'");
+    println!();
+    println!("Exit status:");
+    println!("  0  Every file was processed");
+    println!("  1  Fatal error: invalid arguments, path or journal; nothing (or nothing more) was done");
+    println!("  2  Partial failure: the run finished but some files or the journal failed");
+    println!("  130  Cancelled with Ctrl-C; the files processed so far stay organized (and can be undone),");
+    println!("       a second Ctrl-C exits at once");
+}
+
+fn run_undo(args: &[String]) {
+    let mut positional = Vec::new();
+    let mut output: Option<PathBuf> = None;
+    let mut output_format = OutputFormat::default();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                process::exit(EXIT_SUCCESS);
+            }
+            "-o" | "--output" => {
+                i += 1;
+                let Some(dir) = args.get(i) else {
+                    eprintln!("Error: --output requires a directory");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                output = Some(PathBuf::from(dir));
+            }
+            "--output-format" => output_format = parse_output_format(args, &mut i),
+            arg if !arg.starts_with('-') => positional.push(arg),
+            arg => {
+                eprintln!("Error: Unknown option '{}'", arg);
+                print_usage();
+                process::exit(EXIT_FATAL);
+            }
+        }
+        i += 1;
+    }
+
+    let (path, run_id) = match positional.as_slice() {
+        [path] => (*path, None),
+        [path, run_id] => (*path, Some(*run_id)),
+        _ => {
+            eprintln!("Error: Usage: organizer-cli undo <path> [<run-id>] [--output <dir>] [--output-format <FORMAT>]");
+            process::exit(EXIT_FATAL);
+        }
+    };
+
+    let result = FileOrganizerCore::undo_with_output(path, output.as_deref(), run_id)
+        .unwrap_or_else(|e| fail(&e, output_format));
+    match output_format {
+        OutputFormat::Text => {
+            println!("{}", result.summary);
+
+            if let Some(errors) = &result.errors {
+                println!("\nErrors:");
+                for error in errors {
+                    println!("  - {}", error);
+                }
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default()),
+        OutputFormat::Ndjson => println!("{}", RunEvent::Undo(&result).to_line()),
+    }
+
+    if result.errors.is_some() {
+        process::exit(EXIT_PARTIAL);
+    }
+}
+
+fn run_dupes(args: &[String]) {
+    let mut path: Option<&str> = None;
+    // Los duplicados suelen estar repartidos por subcarpetas
+    let mut scan = ScanOptions::recursive();
+    let mut keep = KeepRule::default();
+    let mut action: Option<DuplicateAction> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut dry_run = false;
+    let mut output_format = OutputFormat::default();
+
+    let mut i = 0;
+    while i < args.len() {
+        if parse_scan_option(args, &mut i, &mut scan) {
+            i += 1;
+            continue;
+        }
+
+        match args[i].as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                process::exit(EXIT_SUCCESS);
+            }
+            "--keep" => {
+                i += 1;
+                let value = args.get(i).map(String::as_str).unwrap_or_default();
+                keep = match value.parse() {
+                    Ok(rule) => rule,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                };
+            }
+            "--keep-in" => {
+                i += 1;
+                let Some(folder) = args.get(i) else {
+                    eprintln!("Error: --keep-in requires a folder");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                keep = KeepRule::InFolder(PathBuf::from(folder));
+            }
+            "--action" => {
+                i += 1;
+                let value = args.get(i).map(String::as_str).unwrap_or_default();
+                action = match value.parse() {
+                    Ok(action) => Some(action),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                };
+            }
+            "-o" | "--output" => {
+                i += 1;
+                let Some(dir) = args.get(i) else {
+                    eprintln!("Error: --output requires a directory");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                output = Some(PathBuf::from(dir));
+            }
+            "-n" | "--dry-run" => {
+                dry_run = true;
+            }
+            "--output-format" => output_format = parse_output_format(args, &mut i),
+            arg if !arg.starts_with('-') && path.is_none() => path = Some(arg),
+            arg => {
+                eprintln!("Error: Unexpected argument '{}'", arg);
+                print_usage();
+                process::exit(EXIT_FATAL);
+            }
+        }
+        i += 1;
+    }
+
+    let Some(path) = path else {
+        eprintln!("Error: Usage: organizer-cli dupes <path> [OPTIONS]");
+        process::exit(EXIT_FATAL);
+    };
+
+    let report = FileOrganizerCore::find_duplicates(path, &scan, output.as_deref())
+        .unwrap_or_else(|e| fail(&e, output_format));
+
+    // Archivos que no se pudieron leer al buscar
+    let partial = if report.errors.is_empty() { EXIT_SUCCESS } else { EXIT_PARTIAL };
+
+    // Sin --action solo se informa; con --dry-run se muestra qué se haría
+    let resolution = action.map(|action| (&keep, action));
+    match output_format {
+        OutputFormat::Text => print_duplicates(&report, resolution),
+        OutputFormat::Ndjson => print_duplicate_events(&report, resolution),
+        // El documento se escribe al final, con lo que se haya hecho
+        OutputFormat::Json => {}
+    }
+    let Some(action) = action.filter(|_| !dry_run && !report.groups.is_empty()) else {
+        if output_format == OutputFormat::Json {
+            println!("{}", DuplicatesDocument::new(&report, resolution, None).to_json());
+        }
+        process::exit(partial);
+    };
+
+    if output_format == OutputFormat::Text {
+        println!();
+    }
+    let result = FileOrganizerCore::resolve_duplicates(&report, &keep, action, output.as_deref())
+        .unwrap_or_else(|e| fail(&e, output_format));
+    match output_format {
+        OutputFormat::Text => {
+            println!("{}", result.summary);
+
+            if let Some(errors) = &result.errors {
+                println!("Errors:");
+                for error in errors {
+                    println!("  - {}", error);
+                }
+            }
+
+            if let Some(run_id) = &result.run_id {
+                let output = output
+                    .as_ref()
+                    .map(|dir| format!(" --output {}", dir.display()))
+                    .unwrap_or_default();
+                println!("\nRun id: {} (revert with: organizer-cli undo {} {}{})", run_id, path, run_id, output);
+            }
+        }
+        OutputFormat::Json => println!("{}", DuplicatesDocument::new(&report, resolution, Some(&result)).to_json()),
+        OutputFormat::Ndjson => println!("{}", RunEvent::Resolved(&result).to_line()),
+    }
+
+    if result.errors.is_some() {
+        process::exit(EXIT_PARTIAL);
+    }
+    process::exit(partial);
+}
+
+/// Un evento `duplicate` por grupo y, al final, los totales en `duplicates`
+fn print_duplicate_events(report: &DuplicateReport, resolution: Option<(&KeepRule, DuplicateAction)>) {
+    let keep = resolution.map(|(keep, _)| keep.within(&report.root));
+    for group in &report.groups {
+        let kept = keep.as_ref().map(|keep| group.keeper(keep));
+        println!("{}", RunEvent::Duplicate { group, kept }.to_line());
+    }
+    println!("{}", RunEvent::duplicates(report).to_line());
+}
+
+/// Lista los grupos; con una resolución, marca qué copia se conserva y qué se
+/// hace con las demás
+fn print_duplicates(report: &DuplicateReport, resolution: Option<(&KeepRule, DuplicateAction)>) {
+    let resolution = resolution.map(|(keep, action)| (keep.within(&report.root), action));
+
+    if report.groups.is_empty() {
+        println!("No duplicates found ({} file(s) scanned).", report.files_scanned);
+    } else {
+        for group in &report.groups {
+            println!(
+                "{} copies of {} ({}):",
+                group.files.len(),
+                format_size(group.size),
+                &group.hash[..12]
+            );
+            let kept = resolution.as_ref().map(|(keep, _)| group.keeper(keep));
+            for file in &group.files {
+                let mark = match (kept, &resolution) {
+                    (Some(kept), _) if kept == file => "[KEEP] ".to_string(),
+                    (Some(_), Some((_, action))) => format!("[{}] ", action.name().to_uppercase()),
+                    _ => String::new(),
+                };
+                println!("  - {}{}", mark, display_relative(file, &report.root));
+            }
+            println!();
+        }
+
+        println!(
+            "{} group(s), {} duplicate file(s) in {} scanned, {} reclaimable",
+            report.groups.len(),
+            report.duplicate_files(),
+            report.files_scanned,
+            format_size(report.reclaimable_bytes)
+        );
+    }
+
+    if !report.errors.is_empty() {
+        println!("\nErrors:");
+        for error in &report.errors {
+            println!("  - {}", error);
+        }
+    }
+}
+
+fn display_relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+fn print_type_mismatches(mismatches: &[TypeMismatch], root: &Path) {
+    if mismatches.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("Extension does not match content:");
+    for mismatch in mismatches {
+        println!(
+            "  - {}: .{} but looks like .{} ({})",
+            display_relative(&mismatch.path, root),
+            mismatch.extension,
+            mismatch.detected.extension,
+            mismatch.detected.mime_type
+        );
+    }
+}
+
+fn print_plan(plan: &OrganizePlan) {
+    if plan.is_empty() {
+        println!("No files to organize.");
+    } else {
+        let action = match plan.mode {
+            FileOperationMode::Cut => "MOVE",
+            FileOperationMode::Copy => "COPY",
+        };
+
+        for operation in &plan.operations {
+            println!(
+                "  [{}] {} -> {} ({})",
+                action,
+                display_relative(&operation.source, &plan.root),
+                display_relative(&operation.destination, &plan.root),
+                operation.reason
+            );
+            for conflict in &operation.conflicts {
+                println!("         conflict: {}", conflict);
+            }
+        }
+
+        println!();
+        println!(
+            "{} file(s) planned, {} with conflicts (policy: {})",
+            plan.operations.len(),
+            plan.conflict_count(),
+            plan.conflict_policy
+        );
+    }
+
+    print_type_mismatches(&plan.type_mismatches, &plan.root);
+    print_skipped(&plan.skipped, &plan.root);
+    print_ignored(&plan.ignored, &plan.root);
+    print_filtered(plan.filtered.len());
+    print_unreadable(&plan.unreadable);
+}
+
+fn print_skipped(skipped: &[SkippedFile], root: &Path) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("Left in place:");
+    for file in skipped {
+        println!("  - {}: {}", display_relative(&file.path, root), file.reason);
+    }
+}
+
+fn print_ignored(ignored: &[IgnoredPath], root: &Path) {
+    if ignored.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("Ignored by .organizerignore:");
+    for (ignore_file, count) in count_by_ignore_file(ignored) {
+        println!("  - {}: {} item(s)", display_relative(ignore_file, root), count);
+    }
+}
+
+fn print_filtered(filtered: usize) {
+    if filtered > 0 {
+        println!();
+        println!("Filtered out: {} file(s) did not match the size/date/name/hidden filters", filtered);
+    }
+}
+
+fn print_unreadable(unreadable: &[UnreadablePath]) {
+    if unreadable.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("Could not read {} path(s); they were left untouched:", unreadable.len());
+    for path in unreadable {
+        println!("  - {}", path.error);
+    }
+}
+
+/// Opciones que eligen qué archivos se recorren, comunes a organizar y a `dupes`.
+/// Devuelve `false` si `args[*i]` no es una de ellas.
+fn parse_scan_option(args: &[String], i: &mut usize, scan: &mut ScanOptions) -> bool {
+    match args[*i].as_str() {
+        "-x" | "--exclude" => {
+            *i += 1;
+            let Some(pattern) = args.get(*i) else {
+                eprintln!("Error: --exclude requires a pattern");
+                print_usage();
+                process::exit(EXIT_FATAL);
+            };
+            scan.exclude.push(pattern.clone());
+        }
+        "-i" | "--include" => {
+            *i += 1;
+            let Some(pattern) = args.get(*i) else {
+                eprintln!("Error: --include requires a pattern");
+                print_usage();
+                process::exit(EXIT_FATAL);
+            };
+            scan.include.push(pattern.clone());
+        }
+        "--no-ignore" => {
+            scan.ignore_files = false;
+        }
+        "--symlinks" => {
+            *i += 1;
+            let value = args.get(*i).map(String::as_str).unwrap_or_default();
+            scan.symlinks = match value.parse() {
+                Ok(policy) => policy,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                }
+            };
+        }
+        "--min-size" | "--max-size" => {
+            let flag = args[*i].clone();
+            *i += 1;
+            let value = args.get(*i).map(String::as_str).unwrap_or_default();
+            let size = match parse_size(value) {
+                Ok(size) => size,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", flag, e);
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                }
+            };
+            if flag == "--min-size" {
+                scan.filter.min_size = Some(size);
+            } else {
+                scan.filter.max_size = Some(size);
+            }
+        }
+        "--modified-before" | "--modified-after" => {
+            let flag = args[*i].clone();
+            *i += 1;
+            let value = args.get(*i).map(String::as_str).unwrap_or_default();
+            let bound: DateBound = match value.parse() {
+                Ok(bound) => bound,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", flag, e);
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                }
+            };
+            if flag == "--modified-before" {
+                scan.filter.modified_before = Some(bound);
+            } else {
+                scan.filter.modified_after = Some(bound);
+            }
+        }
+        "--name" => {
+            *i += 1;
+            let Some(pattern) = args.get(*i) else {
+                eprintln!("Error: --name requires a pattern");
+                print_usage();
+                process::exit(EXIT_FATAL);
+            };
+            scan.filter.name_patterns.push(pattern.clone());
+        }
+        "--skip-hidden" => {
+            scan.filter.include_hidden = false;
+        }
+        "--match-any" => {
+            scan.filter.match_any = true;
+        }
+        "-d" | "--depth" => {
+            *i += 1;
+            scan.max_depth = match args.get(*i).map(String::as_str) {
+                Some("all") | Some("unlimited") => None,
+                Some(value) => match value.parse() {
+                    Ok(depth) => Some(depth),
+                    Err(_) => {
+                        eprintln!("Error: Invalid depth '{}' (expected a number or 'all')", value);
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                },
+                None => {
+                    eprintln!("Error: --depth requires a value");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                }
+            };
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Pregunta en la terminal qué hacer con un conflicto (`--on-conflict ask`)
+fn ask_conflict(operation: &PlannedOperation) -> ConflictPolicy {
+    let stdin = io::stdin();
+    loop {
+        // En stderr, para no mezclarse con la salida JSON
+        eprint!(
+            "'{}' already exists (from '{}'). [s]kip, [o]verwrite, [r]ename, [p]refix, [n]ewest, [l]argest? ",
+            operation.destination.display(),
+            operation.source.display()
+        );
+        let _ = io::stderr().flush();
+
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer).unwrap_or(0) == 0 {
+            return ConflictPolicy::Skip;
+        }
+
+        match answer.trim() {
+            "s" | "skip" => return ConflictPolicy::Skip,
+            "o" | "overwrite" => return ConflictPolicy::Overwrite,
+            "r" | "rename" => return ConflictPolicy::RenameWithSuffix,
+            "p" | "prefix" => return ConflictPolicy::PrefixSourceFolder,
+            "n" | "newest" => return ConflictPolicy::KeepNewest,
+            "l" | "largest" => return ConflictPolicy::KeepLargest,
+            _ => eprintln!("Please answer s, o, r, p, n or l."),
+        }
+    }
 }
 
 fn main() {
@@ -28,21 +697,203 @@ fn main() {
     if args.len() < 2 {
         eprintln!("Error: No path provided");
         print_usage();
-        process::exit(1);
+        process::exit(EXIT_FATAL);
+    }
+
+    if args[1] == "undo" {
+        run_undo(&args[2..]);
+        return;
+    }
+
+    if args[1] == "dupes" {
+        run_dupes(&args[2..]);
+        return;
     }
 
     let mut path: Option<String> = None;
-    let mut mode = FileOperationMode::Cut;
+    let mut options = OrganizeOptions::default();
+    let mut dry_run = false;
+    let mut stream = false;
+    let mut report: Option<PathBuf> = None;
+    let mut report_format: Option<ReportFormat> = None;
+    let mut output_format = OutputFormat::default();
+
+    // Configuración de las estrategias; la elegida se resuelve al final en el registro
+    let mut strategy_name: Option<String> = None;
+    let mut rules: Option<RuleSet> = None;
+    let mut date_grouping: Option<DateGrouping> = None;
+    let mut patterns: Vec<NamePattern> = Vec::new();
+    let mut template: Option<String> = None;
 
     let mut i = 1;
     while i < args.len() {
+        if parse_scan_option(&args, &mut i, &mut options.scan) {
+            i += 1;
+            continue;
+        }
+
         match args[i].as_str() {
             "-h" | "--help" => {
                 print_usage();
-                process::exit(0);
+                process::exit(EXIT_SUCCESS);
             }
             "-c" | "--copy" => {
-                mode = FileOperationMode::Copy;
+                options.mode = FileOperationMode::Copy;
+            }
+            "-o" | "--output" => {
+                i += 1;
+                let Some(dir) = args.get(i) else {
+                    eprintln!("Error: --output requires a directory");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                options.output_dir = Some(PathBuf::from(dir));
+            }
+            "-n" | "--dry-run" => {
+                dry_run = true;
+            }
+            "--stream" => {
+                stream = true;
+            }
+            "--verify" => {
+                options.verify_transfers = true;
+            }
+            "-j" | "--jobs" => {
+                i += 1;
+                options.jobs = match args.get(i).map(String::as_str) {
+                    Some(value) => match value.parse() {
+                        Ok(jobs) if jobs > 0 => jobs,
+                        _ => {
+                            eprintln!("Error: Invalid job count '{}' (expected a number of at least 1)", value);
+                            print_usage();
+                            process::exit(EXIT_FATAL);
+                        }
+                    },
+                    None => {
+                        eprintln!("Error: --jobs requires a value");
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                };
+            }
+            "-s" | "--strategy" => {
+                i += 1;
+                let Some(name) = args.get(i) else {
+                    eprintln!("Error: --strategy requires a name");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                strategy_name = Some(name.clone());
+            }
+            "--categories" => {
+                strategy_name = Some("category".to_string());
+            }
+            "--rules" => {
+                i += 1;
+                let Some(file) = args.get(i) else {
+                    eprintln!("Error: --rules requires a file");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                rules = match RuleSet::load(Path::new(file)) {
+                    Ok(rules) => Some(rules),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(EXIT_FATAL);
+                    }
+                };
+                strategy_name = Some("category".to_string());
+            }
+            "--detect" => {
+                options.type_detection = TypeDetection::ExtensionFirst;
+            }
+            "--prefer-content" => {
+                options.type_detection = TypeDetection::ContentFirst;
+            }
+            "--by-date" => {
+                strategy_name = Some("date".to_string());
+            }
+            "--date-source" => {
+                i += 1;
+                let value = args.get(i).map(String::as_str).unwrap_or_default();
+                date_grouping.get_or_insert_default().source = match value.parse() {
+                    Ok(source) => source,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                };
+                strategy_name = Some("date".to_string());
+            }
+            "--date-template" => {
+                i += 1;
+                let Some(template) = args.get(i) else {
+                    eprintln!("Error: --date-template requires a template");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                date_grouping.get_or_insert_default().template = template.clone();
+                strategy_name = Some("date".to_string());
+            }
+            "--by-size" => {
+                strategy_name = Some("size".to_string());
+            }
+            "--by-pattern" => {
+                i += 1;
+                let spec = args.get(i).map(String::as_str).unwrap_or_default();
+                match NamePattern::parse(spec) {
+                    Ok(pattern) => patterns.push(pattern),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                }
+                strategy_name = Some("pattern".to_string());
+            }
+            "--template" => {
+                i += 1;
+                let Some(value) = args.get(i) else {
+                    eprintln!("Error: --template requires a template");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                template = Some(value.clone());
+            }
+            "--on-conflict" => {
+                i += 1;
+                let value = args.get(i).map(String::as_str).unwrap_or_default();
+                options.conflict_policy = match value.parse() {
+                    Ok(policy) => policy,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                };
+            }
+            "--report" => {
+                i += 1;
+                let Some(file) = args.get(i) else {
+                    eprintln!("Error: --report requires a file");
+                    print_usage();
+                    process::exit(EXIT_FATAL);
+                };
+                report = Some(PathBuf::from(file));
+            }
+            "--output-format" => output_format = parse_output_format(&args, &mut i),
+            "--report-format" => {
+                i += 1;
+                let value = args.get(i).map(String::as_str).unwrap_or_default();
+                report_format = match value.parse() {
+                    Ok(format) => Some(format),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        print_usage();
+                        process::exit(EXIT_FATAL);
+                    }
+                };
             }
             arg if !arg.starts_with('-') => {
                 if path.is_none() {
@@ -50,13 +901,13 @@ fn main() {
                 } else {
                     eprintln!("Error: Unexpected argument '{}'", arg);
                     print_usage();
-                    process::exit(1);
+                    process::exit(EXIT_FATAL);
                 }
             }
             arg => {
                 eprintln!("Error: Unknown option '{}'", arg);
                 print_usage();
-                process::exit(1);
+                process::exit(EXIT_FATAL);
             }
         }
         i += 1;
@@ -67,31 +918,157 @@ fn main() {
         None => {
             eprintln!("Error: No path provided");
             print_usage();
-            process::exit(1);
+            process::exit(EXIT_FATAL);
         }
     };
 
-    let operation = match mode {
+    // La plantilla usa las mismas categorías y el mismo origen de fecha que las estrategias
+    if let Some(template) = template {
+        let mut template = PathTemplate::new(template);
+        if let Some(rules) = &rules {
+            template.rules = rules.clone();
+        }
+        if let Some(date_grouping) = &date_grouping {
+            template.date_source = date_grouping.source;
+        }
+        options.template = Some(template);
+    }
+
+    // Las estrategias configuradas por opciones reemplazan a las de por defecto
+    let mut registry = StrategyRegistry::with_builtins();
+    if let Some(rules) = rules {
+        registry.register(Arc::new(CategoryStrategy::new(rules)));
+    }
+    if let Some(date_grouping) = date_grouping {
+        registry.register(Arc::new(DateStrategy::new(date_grouping)));
+    }
+    if !patterns.is_empty() {
+        registry.register(Arc::new(NamePatternStrategy { patterns, fallback: None }));
+    }
+    if let Some(name) = strategy_name {
+        options.strategy = match registry.get(&name) {
+            Some(strategy) => strategy,
+            None => {
+                let names: Vec<&str> = registry.names().collect();
+                eprintln!("Error: Unknown strategy '{}' (available: {})", name, names.join(", "));
+                process::exit(EXIT_FATAL);
+            }
+        };
+    }
+
+    let operation = match options.mode {
         FileOperationMode::Cut => "Moving",
         FileOperationMode::Copy => "Copying",
     };
-    println!("{} files from: {}", operation, path);
-    println!();
+    let text = output_format == OutputFormat::Text;
 
-    match FileOrganizerCore::organize_by_extension(&path, mode) {
-        Ok(result) => {
-            println!("{}", result.summary);
+    if dry_run {
+        if text {
+            println!("Dry run: {} files from: {}", operation.to_lowercase(), path);
+            println!();
+        }
 
-            if let Some(errors) = &result.errors {
-                println!("\nErrors:");
-                for error in errors {
-                    println!("  - {}", error);
+        let plan = FileOrganizerCore::plan_organize(&path, &options).unwrap_or_else(|e| fail(&e, output_format));
+        match output_format {
+            OutputFormat::Text => print_plan(&plan),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&plan).unwrap_or_default()),
+            OutputFormat::Ndjson => {
+                for operation in &plan.operations {
+                    println!("{}", RunEvent::Planned(operation).to_line());
                 }
+                println!("{}", RunEvent::plan(&plan).to_line());
             }
         }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            process::exit(1);
+        if report.is_some() {
+            eprintln!("\nNote: --report is ignored with --dry-run");
+        }
+        if stream {
+            eprintln!("\nNote: --stream is ignored with --dry-run");
         }
+        return;
+    }
+
+    if text {
+        println!("{} files from: {}", operation, path);
+        println!();
+    }
+
+    // El primer Ctrl-C cancela entre archivos; el segundo sale en el acto
+    let flag = options.cancel.flag();
+    let _ = signal_hook::flag::register_conditional_shutdown(SIGINT, EXIT_CANCELLED, Arc::clone(&flag));
+    let _ = signal_hook::flag::register(SIGINT, flag);
+
+    let print_outcome = |outcome: &FileOutcome| {
+        if output_format == OutputFormat::Ndjson {
+            println!("{}", RunEvent::File(outcome).to_line());
+        }
+    };
+    let result = if stream {
+        FileOrganizerCore::organize_streaming_with_observer(&path, &options, &|_| {}, ask_conflict, print_outcome)
+    } else {
+        FileOrganizerCore::plan_organize(&path, &options).and_then(|plan| {
+            FileOrganizerCore::execute_plan_with_observer(&plan, &|_| {}, ask_conflict, print_outcome)
+        })
+    }
+    .unwrap_or_else(|e| fail(&e, output_format));
+    let run_report = RunReport::new(Path::new(&path), options.mode, &result);
+
+    match output_format {
+        OutputFormat::Text => print_result(&result, &path, options.output_dir.as_deref()),
+        OutputFormat::Json => println!("{}", run_report.render(ReportFormat::Json)),
+        OutputFormat::Ndjson => println!("{}", RunEvent::Summary(run_report.summary()).to_line()),
+    }
+
+    if let Some(file) = report {
+        let format = report_format
+            .or_else(|| ReportFormat::from_path(&file))
+            .unwrap_or_default();
+        match run_report.write(&file, format) {
+            Ok(()) if text => println!("\nReport ({}) written to: {}", format, file.display()),
+            Ok(()) => {}
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(EXIT_PARTIAL);
+            }
+        }
+    }
+
+    if result.cancelled {
+        eprintln!("Cancelled: the remaining files were left untouched");
+        process::exit(EXIT_CANCELLED);
+    }
+    process::exit(if result.has_errors() { EXIT_PARTIAL } else { EXIT_SUCCESS });
+}
+
+/// Resumen en texto de una ejecución terminada
+fn print_result(result: &OrganizerResult, path: &str, output_dir: Option<&Path>) {
+    println!("{}", result.summary);
+
+    if !result.conflicts.is_empty() {
+        println!("Conflicts:");
+        for conflict in &result.conflicts {
+            println!("  - {}: {}", conflict.destination.display(), conflict.resolution);
+        }
+    }
+
+    print_type_mismatches(&result.type_mismatches, Path::new(path));
+    print_skipped(&result.skipped, Path::new(path));
+    print_ignored(&result.ignored, Path::new(path));
+    print_filtered(result.filtered.len());
+    print_unreadable(&result.unreadable);
+
+    let errors: Vec<_> = result.all_errors().collect();
+    if !errors.is_empty() {
+        println!("\nErrors:");
+        for error in errors {
+            println!("  - {}", error);
+        }
+    }
+
+    if let Some(run_id) = &result.run_id {
+        let output = output_dir
+            .map(|dir| format!(" --output {}", dir.display()))
+            .unwrap_or_default();
+        println!("\nRun id: {} (revert with: organizer-cli undo {} {}{})", run_id, path, run_id, output);
     }
 }
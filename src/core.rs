@@ -1,20 +1,367 @@
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Component, Path, PathBuf};
 use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-// Define a callback type for progress updates
-pub type ProgressCallback = dyn Fn(usize, usize) + Send; // (current, total)
+use serde::{Deserialize, Serialize};
+
+mod cancel;
+mod conflict;
+mod dates;
+mod dupes;
+mod error;
+mod filter;
+mod journal;
+mod outcome;
+mod plan;
+mod progress;
+mod report;
+mod rules;
+mod scan;
+mod sniff;
+mod strategy;
+mod template;
+#[cfg(test)]
+mod testing;
+mod transfer;
+
+pub use cancel::CancelToken;
+pub use conflict::{ConflictPolicy, ConflictRecord, ConflictResolution};
+pub use dates::{exif_date_taken, file_date, DateGrouping, DateSource, DEFAULT_DATE_TEMPLATE};
+pub use dupes::{DuplicateAction, DuplicateGroup, DuplicateReport, KeepRule, QUARANTINE_DIR};
+pub use error::{Operation, OrganizerError};
+pub use filter::{format_size, parse_size, DateBound, FileFilter};
+pub use journal::{EntryAction, JournalEntry, JournalWriter, RunJournal, JOURNAL_DIR};
+pub use outcome::{extension_map, FileOutcome, OutcomeStatus};
+pub use plan::{OrganizePlan, PlanConflict, PlannedOperation, SkippedFile};
+pub use progress::Progress;
+pub use report::{DuplicatesDocument, ReportFormat, RunEvent, RunReport, RunSummary};
+pub use rules::{mime_from_extension, CategoryRule, RuleSet};
+pub use scan::{count_by_ignore_file, IgnoredPath, ScanOptions, SymlinkPolicy, UnreadablePath, IGNORE_FILE_NAME};
+use scan::{ScanItem, ScannedFile, Scanner, Walk};
+pub use sniff::{detect_file_type, detect_from_bytes, DetectedType, TypeDetection, TypeMismatch};
+pub use strategy::{
+    CategoryStrategy, DateStrategy, ExtensionStrategy, FileEntry, NamePattern, NamePatternStrategy,
+    OrganizeStrategy, Placement, SizeBucket, SizeBucketStrategy, StrategyRegistry,
+};
+pub use template::{PathTemplate, DEFAULT_PATH_TEMPLATE, TEMPLATE_PLACEHOLDERS};
+
+/// Carpeta de destino por defecto, dentro de la carpeta que se organiza
+pub const DEFAULT_OUTPUT_DIR: &str = "Organizer";
+
+/// Callback que recibe el avance de una ejecución. Es `Send` para que una
+/// interfaz pueda ejecutar el plan en otro hilo y reenviar el avance
+pub type ProgressCallback<'a> = dyn Fn(&Progress) + Send + 'a;
 
 /// Operation mode: either move (cut) or copy files
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum FileOperationMode {
+    #[default]
     Cut,  // Move files
     Copy, // Copy files
 }
 
-impl Default for FileOperationMode {
+/// Opciones de una ejecución de organización
+#[derive(Debug, Clone)]
+pub struct OrganizeOptions {
+    pub mode: FileOperationMode,
+    /// Carpeta raíz de destino; `None` = `Organizer/` dentro de la carpeta organizada.
+    /// Puede estar fuera del árbol que se recorre (otro disco, por ejemplo).
+    pub output_dir: Option<PathBuf>,
+    /// Criterio para repartir los archivos en carpetas
+    pub strategy: Arc<dyn OrganizeStrategy>,
+    /// Plantilla para la ruta final de cada archivo. La estrategia sigue
+    /// decidiendo qué archivos se mueven; la plantilla, dónde y con qué nombre.
+    pub template: Option<PathTemplate>,
+    /// Profundidad del recorrido y patrones de inclusión/exclusión
+    pub scan: ScanOptions,
+    pub conflict_policy: ConflictPolicy,
+    /// Comprobar con un hash cada copia (incluidos los movimientos entre discos)
+    pub verify_transfers: bool,
+    /// Archivos que se transfieren a la vez (1 = uno tras otro)
+    pub jobs: usize,
+    /// Si se lee el contenido de los archivos para reconocer su tipo
+    pub type_detection: TypeDetection,
+    /// Permite detener la ejecución desde otro hilo; pasa al plan
+    pub cancel: CancelToken,
+}
+
+impl Default for OrganizeOptions {
     fn default() -> Self {
-        FileOperationMode::Cut
+        OrganizeOptions {
+            mode: FileOperationMode::default(),
+            output_dir: None,
+            strategy: Arc::new(ExtensionStrategy),
+            template: None,
+            scan: ScanOptions::default(),
+            conflict_policy: ConflictPolicy::default(),
+            verify_transfers: false,
+            jobs: 1,
+            type_detection: TypeDetection::default(),
+            cancel: CancelToken::new(),
+        }
+    }
+}
+
+/// Estado compartido de una ejecución mientras se aplica el plan
+struct RunState {
+    /// Diario que se escribe en disco según termina cada archivo
+    journal: JournalWriter,
+    /// Si el diario deja de poder escribirse, la ejecución para tras el archivo en curso
+    journal_error: Option<OrganizerError>,
+    conflicts: Vec<ConflictRecord>,
+    created_folders: usize,
+    /// Bytes de los archivos ya terminados, para el avance
+    bytes_done: u64,
+    cancelled: bool,
+    /// Resultado de cada operación, en el orden en que se empezó
+    outcomes: Vec<Option<FileOutcome>>,
+}
+
+impl RunState {
+    /// Guarda el resultado de la operación `idx` y avisa al observador
+    fn finish<O: FnMut(&FileOutcome)>(&mut self, idx: usize, operation: &PlannedOperation, outcome: FileOutcome, observer: &mut O) {
+        observer(&outcome);
+        self.bytes_done += operation.size;
+        self.cancelled |= outcome.status == OutcomeStatus::Cancelled;
+        self.outcomes[idx] = Some(outcome);
+    }
+}
+
+/// Archivo con la carpeta creada y el conflicto resuelto, listo para transferir
+struct PreparedOperation {
+    outcome: FileOutcome,
+    started: Instant,
+    created_folder: bool,
+    /// Destino del origen si es un enlace simbólico, leído antes de trasladarlo
+    link_target: Option<PathBuf>,
+    /// Dónde se apartó el archivo que ocupaba el destino, y su tamaño
+    set_aside: Option<(PathBuf, u64)>,
+}
+
+enum Prepared {
+    Ready(PreparedOperation),
+    /// No hay nada que transferir: falló la carpeta o el conflicto lo omitió
+    Finished(FileOutcome),
+}
+
+/// Decide el destino de cada archivo según aparece en el recorrido y guarda
+/// lo que se queda fuera; lo usan tanto el plan como la organización en streaming
+struct Planner<'a> {
+    root: &'a Path,
+    options: &'a OrganizeOptions,
+    organizer_path: PathBuf,
+    /// Destinos ya reclamados por otro archivo del plan
+    claimed: HashMap<PathBuf, PathBuf>,
+    type_mismatches: Vec<TypeMismatch>,
+    skipped: Vec<SkippedFile>,
+    ignored: Vec<IgnoredPath>,
+    filtered: Vec<PathBuf>,
+    unreadable: Vec<UnreadablePath>,
+}
+
+impl<'a> Planner<'a> {
+    fn new(root: &'a Path, options: &'a OrganizeOptions, organizer_path: PathBuf) -> Self {
+        Planner {
+            root,
+            options,
+            organizer_path,
+            claimed: HashMap::new(),
+            type_mismatches: Vec::new(),
+            skipped: Vec::new(),
+            ignored: Vec::new(),
+            filtered: Vec::new(),
+            unreadable: Vec::new(),
+        }
+    }
+
+    /// Anota lo que encontró el recorrido; devuelve la operación si es un archivo que se organiza
+    fn add(&mut self, item: ScanItem) -> Option<PlannedOperation> {
+        match item {
+            ScanItem::File(file) => self.plan_file(file),
+            ScanItem::Ignored(ignored) => {
+                self.ignored.push(ignored);
+                None
+            }
+            ScanItem::Filtered(path) => {
+                self.filtered.push(path);
+                None
+            }
+            ScanItem::Skipped { path, reason } => {
+                self.skipped.push(SkippedFile { path, reason });
+                None
+            }
+            ScanItem::Unreadable { path, error } => {
+                self.unreadable.push(UnreadablePath::new(path, &error));
+                None
+            }
+        }
+    }
+
+    fn plan_file(&mut self, file: ScannedFile) -> Option<PlannedOperation> {
+        let options = self.options;
+        let (entry, mismatch) = FileOrganizerCore::file_entry(self.root, file, options.type_detection);
+        self.type_mismatches.extend(mismatch);
+        let file_path = entry.path.clone();
+
+        let (relative_path, reason) = match options.strategy.place(&entry) {
+            Placement::Destination { relative_path, reason } => (relative_path, reason),
+            Placement::Skip { reason } => {
+                self.skipped.push(SkippedFile { path: file_path, reason });
+                return None;
+            }
+        };
+
+        let relative_path = match &options.template {
+            Some(template) => {
+                let folder = relative_path.parent().unwrap_or(Path::new(""));
+                match template.render(&entry, folder) {
+                    Ok(rendered) => rendered,
+                    Err(reason) => {
+                        self.skipped.push(SkippedFile { path: file_path, reason });
+                        return None;
+                    }
+                }
+            }
+            None => relative_path,
+        };
+
+        // La estrategia no puede sacar archivos de `Organizer/`
+        if relative_path.is_absolute()
+            || relative_path.file_name().is_none()
+            || relative_path.components().any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)))
+        {
+            self.skipped.push(SkippedFile {
+                path: file_path,
+                reason: format!("destino no válido: {}", relative_path.display()),
+            });
+            return None;
+        }
+
+        let folder_name = relative_path.parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| ".".to_string());
+        let destination = self.organizer_path.join(&relative_path);
+
+        let mut conflicts = Vec::new();
+        if destination.exists() {
+            conflicts.push(PlanConflict::DestinationExists);
+        }
+        match self.claimed.get(&destination) {
+            Some(other) => conflicts.push(PlanConflict::DuplicateDestination(other.clone())),
+            None => {
+                self.claimed.insert(destination.clone(), file_path.clone());
+            }
+        }
+
+        Some(PlannedOperation {
+            source: file_path,
+            destination,
+            action: options.mode,
+            folder_name,
+            reason,
+            size: entry.metadata.len(),
+            conflicts,
+        })
+    }
+
+    fn into_plan(self, operations: Vec<PlannedOperation>) -> OrganizePlan {
+        let options = self.options;
+        OrganizePlan {
+            root: self.root.to_path_buf(),
+            organizer_path: self.organizer_path,
+            mode: options.mode,
+            conflict_policy: options.conflict_policy,
+            verify_transfers: options.verify_transfers,
+            jobs: options.jobs,
+            operations,
+            type_mismatches: self.type_mismatches,
+            skipped: self.skipped,
+            ignored: self.ignored,
+            filtered: self.filtered,
+            unreadable: self.unreadable,
+            cancelled: false,
+            cancel: options.cancel.clone(),
+        }
+    }
+}
+
+/// De dónde salen las operaciones de una ejecución: un plan ya calculado o un
+/// recorrido que las va planificando según avanza
+trait OperationFeed {
+    fn next_operation(&mut self) -> Option<PlannedOperation>;
+    /// Archivos que hay que procesar en total
+    fn total_files(&self) -> usize;
+    /// Bytes de todos los archivos que hay que procesar
+    fn total_bytes(&self) -> u64;
+    /// Indica si los totales son una estimación
+    fn is_estimate(&self) -> bool;
+}
+
+/// Operaciones de un plan completo, con los totales exactos
+struct PlanFeed<'p> {
+    plan: &'p OrganizePlan,
+    next: usize,
+    bytes_total: u64,
+}
+
+impl<'p> PlanFeed<'p> {
+    fn new(plan: &'p OrganizePlan) -> Self {
+        PlanFeed { plan, next: 0, bytes_total: plan.operations.iter().map(|op| op.size).sum() }
+    }
+}
+
+impl OperationFeed for PlanFeed<'_> {
+    fn next_operation(&mut self) -> Option<PlannedOperation> {
+        let operation = self.plan.operations.get(self.next)?.clone();
+        self.next += 1;
+        Some(operation)
+    }
+
+    fn total_files(&self) -> usize {
+        self.plan.operations.len()
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.bytes_total
+    }
+
+    fn is_estimate(&self) -> bool {
+        false
+    }
+}
+
+/// Operaciones planificadas según las encuentra el recorrido
+struct StreamFeed<'a> {
+    walk: Walk<'a>,
+    planner: Planner<'a>,
+}
+
+impl OperationFeed for StreamFeed<'_> {
+    fn next_operation(&mut self) -> Option<PlannedOperation> {
+        loop {
+            let item = self.walk.next()?;
+            if let Some(operation) = self.planner.add(item) {
+                return Some(operation);
+            }
+        }
+    }
+
+    fn total_files(&self) -> usize {
+        self.walk.estimated_files()
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.walk.estimated_bytes()
+    }
+
+    fn is_estimate(&self) -> bool {
+        !self.walk.is_finished()
     }
 }
 
@@ -22,12 +369,12 @@ pub struct FileOrganizerCore;
 
 impl FileOrganizerCore {
     /// Lista todos los archivos en la ruta especificada
-    pub fn list_files_in_path(path: &str) -> Result<Vec<String>, String> {
+    pub fn list_files_in_path(path: &str) -> Result<Vec<String>, OrganizerError> {
         let path = Path::new(path);
 
         // Verificar si la ruta existe
         if !path.exists() {
-            return Err(format!("La ruta '{}' no existe", path.display()));
+            return Err(OrganizerError::NotFound { path: path.to_path_buf() });
         }
 
         // Verificar si es un archivo o directorio
@@ -37,209 +384,1123 @@ impl FileOrganizerCore {
             return Self::list_files_in_directory(path);
         }
 
-        Err("Ruta no válida".to_string())
+        Err(OrganizerError::NotADirectory { path: path.to_path_buf() })
     }
 
     /// Organiza archivos por extensión en una carpeta "Organizer"
-    pub fn organize_by_extension(path: &str, mode: FileOperationMode) -> Result<OrganizerResult, String> {
+    pub fn organize_by_extension(path: &str, mode: FileOperationMode) -> Result<OrganizerResult, OrganizerError> {
         // Call the new function with a no-op progress callback
-        Self::organize_by_extension_with_progress(path, mode, &|_, _| {})
+        Self::organize_by_extension_with_progress(path, mode, &|_| {})
     }
 
     /// Organiza archivos por extensión en una carpeta "Organizer" con callback de progreso
-    pub fn organize_by_extension_with_progress<F>(
+    pub fn organize_by_extension_with_progress(
         path: &str,
         mode: FileOperationMode,
-        progress_callback: F
-    ) -> Result<OrganizerResult, String>
-    where
-        F: Fn(usize, usize), // (current, total)
+        progress_callback: &ProgressCallback<'_>,
+    ) -> Result<OrganizerResult, OrganizerError>
     {
         // Call the new function with an empty exclusion list
-        Self::organize_by_extension_with_progress_and_exclusions(path, mode, &Vec::new(), progress_callback)
+        Self::organize_by_extension_with_progress_and_exclusions(path, mode, &[], progress_callback)
     }
 
     /// Organiza archivos por extensión en una carpeta "Organizer" con callback de progreso y exclusiones
-    pub fn organize_by_extension_with_progress_and_exclusions<F>(
+    pub fn organize_by_extension_with_progress_and_exclusions(
         path: &str,
         mode: FileOperationMode,
         excluded_items: &[String],
-        progress_callback: F
-    ) -> Result<OrganizerResult, String>
+        progress_callback: &ProgressCallback<'_>,
+    ) -> Result<OrganizerResult, OrganizerError>
+    {
+        let options = OrganizeOptions {
+            mode,
+            scan: ScanOptions {
+                exclude: excluded_items.to_vec(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Self::organize_with_options(path, &options, progress_callback)
+    }
+
+    /// Organiza archivos según las opciones indicadas
+    pub fn organize_with_options(
+        path: &str,
+        options: &OrganizeOptions,
+        progress_callback: &ProgressCallback<'_>,
+    ) -> Result<OrganizerResult, OrganizerError>
+    {
+        let plan = Self::plan_organize(path, options)?;
+        Self::execute_plan(&plan, progress_callback)
+    }
+
+    /// Calcula qué haría la organización sin tocar el disco
+    pub fn plan_organize(path: &str, options: &OrganizeOptions) -> Result<OrganizePlan, OrganizerError> {
+        Self::plan_organize_with_progress(path, options, &|_| {})
+    }
+
+    /// Como `plan_organize`, avisando a `progress_callback` de cada archivo
+    /// encontrado; el total es una estimación hasta que acaba el recorrido
+    pub fn plan_organize_with_progress(
+        path: &str,
+        options: &OrganizeOptions,
+        progress_callback: &ProgressCallback<'_>,
+    ) -> Result<OrganizePlan, OrganizerError> {
+        let path = Path::new(path);
+        let organizer_path = Self::check_organize(path, options)?;
+        let canonical_output = fs::canonicalize(&organizer_path).ok();
+
+        // Recorrer el árbol decidiendo el destino de cada archivo según aparece
+        let scanner = Scanner::new(path, &options.scan, canonical_output.as_deref())?;
+        let mut planner = Planner::new(path, options, organizer_path);
+        let mut operations = Vec::new();
+        let started = Instant::now();
+        let mut walk = scanner.walk();
+        while let Some(item) = walk.next() {
+            // Un plan a medias no sirve para aplicarlo: si se cancela, se devuelve vacío
+            if options.cancel.is_cancelled() {
+                let mut plan = planner.into_plan(Vec::new());
+                plan.cancelled = true;
+                return Ok(plan);
+            }
+            if let ScanItem::File(file) = &item {
+                progress_callback(&walk.progress(&file.path, started.elapsed()));
+            }
+            operations.extend(planner.add(item));
+        }
+        Ok(planner.into_plan(operations))
+    }
+
+    /// Organiza a la vez que recorre la carpeta: los primeros archivos se
+    /// transfieren antes de que termine el recorrido, pensado para árboles con
+    /// millones de archivos. No hay vista previa; los conflictos con `Ask` se omiten.
+    pub fn organize_streaming(
+        path: &str,
+        options: &OrganizeOptions,
+        progress_callback: &ProgressCallback<'_>,
+    ) -> Result<OrganizerResult, OrganizerError> {
+        Self::organize_streaming_with_observer(path, options, progress_callback, |_| ConflictPolicy::Skip, |_| {})
+    }
+
+    /// Organiza a la vez que recorre la carpeta; `resolver` decide los conflictos
+    /// con `Ask` y `observer` recibe cada archivo en cuanto termina. El total que
+    /// llega en el avance es una estimación mientras el recorrido no acaba.
+    pub fn organize_streaming_with_observer<R, O>(
+        path: &str,
+        options: &OrganizeOptions,
+        progress_callback: &ProgressCallback<'_>,
+        resolver: R,
+        observer: O,
+    ) -> Result<OrganizerResult, OrganizerError>
     where
-        F: Fn(usize, usize), // (current, total)
+        R: FnMut(&PlannedOperation) -> ConflictPolicy,
+        O: FnMut(&FileOutcome),
     {
         let path = Path::new(path);
+        let organizer_path = Self::check_organize(path, options)?;
 
-        // Verificar si la ruta existe
-        if !path.exists() {
-            return Err(format!("La ruta '{}' no existe", path.display()));
+        // La carpeta de destino tiene que existir antes de recorrer para poder
+        // reconocerla y no volver a organizar lo que se acaba de mover
+        let created_output = !organizer_path.exists();
+        if created_output {
+            fs::create_dir_all(&organizer_path)
+                .map_err(|e| OrganizerError::io(Operation::CreateDir, &organizer_path, e))?;
         }
+        let canonical_output = fs::canonicalize(&organizer_path).ok();
 
-        // Verificar si es un directorio
-        if !path.is_dir() {
-            return Err("Solo se pueden organizar archivos dentro de directorios".to_string());
+        let scanner = Scanner::new(path, &options.scan, canonical_output.as_deref())?;
+        let mut feed = StreamFeed {
+            walk: scanner.walk(),
+            planner: Planner::new(path, options, organizer_path.clone()),
+        };
+        // Un plan sin operaciones con la configuración de la ejecución; lo que
+        // se queda fuera se añade al terminar el recorrido
+        let mut plan = Planner::new(path, options, organizer_path).into_plan(Vec::new());
+        let journal = JournalWriter::create(&plan.organizer_path, &plan.root, plan.mode)?;
+        let run = Self::run_feed(&plan, journal, &mut feed, progress_callback, resolver, observer);
+
+        let total = feed.total_files();
+        let StreamFeed { planner, .. } = feed;
+        let Planner { type_mismatches, skipped, ignored, filtered, unreadable, .. } = planner;
+        plan.type_mismatches = type_mismatches;
+        plan.skipped = skipped;
+        plan.ignored = ignored;
+        plan.filtered = filtered;
+        plan.unreadable = unreadable;
+
+        // Sin diario no se hizo nada: la carpeta creada para la ejecución sobra
+        let result = Self::build_result(&plan, run, total);
+        if created_output && result.run_id.is_none() {
+            let _ = fs::remove_dir(&plan.organizer_path);
         }
+        Ok(result)
+    }
 
-        // Convertimos los elementos excluidos a minúsculas para comparación
-        let excluded_items_lower: Vec<String> = excluded_items
-            .iter()
-            .map(|item| item.trim().to_lowercase())
-            .collect();
+    /// Comprueba la carpeta y las opciones antes de organizar y devuelve la carpeta de destino
+    fn check_organize(path: &Path, options: &OrganizeOptions) -> Result<PathBuf, OrganizerError> {
+        Self::check_dir(path)?;
 
-        // Recursively collect all files to process (excluding specified items)
-        let mut all_files = Vec::new();
-        Self::collect_files_recursive(path, &excluded_items_lower, &mut all_files)?;
+        options.strategy.validate().map_err(OrganizerError::InvalidConfig)?;
+        if let Some(template) = &options.template {
+            template.validate().map_err(OrganizerError::InvalidConfig)?;
+        }
+
+        let organizer_path = Self::output_root(path, options.output_dir.as_deref());
 
-        if all_files.is_empty() {
-            return Ok(OrganizerResult::empty());
+        // El destino no puede ser la propia carpeta: cada ejecución volvería a mover lo ya organizado
+        let canonical_root = fs::canonicalize(path)
+            .map_err(|e| OrganizerError::io(Operation::ResolvePath, path, e))?;
+        if fs::canonicalize(&organizer_path).ok().as_ref() == Some(&canonical_root) {
+            return Err(OrganizerError::SameSourceAndDestination { path: organizer_path });
         }
+        Ok(organizer_path)
+    }
 
-        // Crear carpeta Organizer
-        let organizer_path = path.join("Organizer");
-        if !organizer_path.exists() {
-            if let Err(e) = fs::create_dir(&organizer_path) {
-                return Err(format!("Error al crear carpeta Organizer: {}", e));
+    /// Busca archivos con el mismo contenido en la carpeta, con las mismas
+    /// opciones de recorrido que la organización. Las copias ya apartadas en
+    /// `Duplicates/` (dentro de `output_dir`) no se vuelven a contar.
+    pub fn find_duplicates(path: &str, scan: &ScanOptions, output_dir: Option<&Path>) -> Result<DuplicateReport, OrganizerError> {
+        Self::find_duplicates_with_progress(path, scan, output_dir, &|_| {})
+    }
+
+    /// Como `find_duplicates`, avisando a `progress_callback` primero de cada
+    /// archivo encontrado (con totales estimados) y después de cada archivo comparado
+    pub fn find_duplicates_with_progress(
+        path: &str,
+        scan: &ScanOptions,
+        output_dir: Option<&Path>,
+        progress_callback: &ProgressCallback<'_>,
+    ) -> Result<DuplicateReport, OrganizerError> {
+        let path = Path::new(path);
+
+        Self::check_dir(path)?;
+
+        // Lo ya organizado son copias de sus propios orígenes, no duplicados. Si
+        // la carpeta de salida es la buscada, solo se aparta la cuarentena.
+        let organizer_path = Self::output_root(path, output_dir);
+        let canonical_output = fs::canonicalize(&organizer_path).ok();
+        let excluded = if canonical_output.is_some() && canonical_output == fs::canonicalize(path).ok() {
+            fs::canonicalize(organizer_path.join(QUARANTINE_DIR)).ok()
+        } else {
+            canonical_output
+        };
+        let scan = Scanner::new(path, scan, excluded.as_deref())?.collect(progress_callback);
+        // Los diarios de las ejecuciones no son archivos del usuario
+        let files = scan
+            .files
+            .into_iter()
+            .map(|file| file.path)
+            .filter(|file| {
+                file.parent()
+                    .and_then(Path::file_name)
+                    .is_none_or(|name| name != JOURNAL_DIR)
+            })
+            .collect();
+
+        // Las carpetas que no se pudieron leer cuentan como errores del informe
+        let mut report = dupes::find_duplicates(path, files, progress_callback);
+        report.errors.splice(0..0, scan.errors);
+        Ok(report)
+    }
+
+    /// Conserva una copia de cada grupo según `keep` y aplica `action` a las
+    /// demás. Todo queda en un diario, así que se puede deshacer con `undo`.
+    pub fn resolve_duplicates(
+        report: &DuplicateReport,
+        keep: &KeepRule,
+        action: DuplicateAction,
+        output_dir: Option<&Path>,
+    ) -> Result<DuplicateResolutionResult, OrganizerError> {
+        let organizer_path = Self::output_root(&report.root, output_dir);
+        let quarantine = organizer_path.join(QUARANTINE_DIR);
+
+        // Una carpeta preferida relativa se entiende dentro de la carpeta buscada
+        let keep = keep.within(&report.root);
+
+        // El diario existe antes de tocar ningún archivo
+        let mut journal = JournalWriter::create(&organizer_path, &report.root, FileOperationMode::Cut)?;
+        let mut resolved = 0;
+        let mut bytes = 0;
+        let mut errors = Vec::new();
+
+        'groups: for group in &report.groups {
+            let kept = group.keeper(&keep);
+
+            // Sin la copia conservada intacta no hay de dónde restaurar las demás
+            match transfer::hash_file(kept) {
+                Ok(hash) if hash.to_hex().as_str() == group.hash => {}
+                Ok(_) => {
+                    errors.push(OrganizerError::Changed { operation: Operation::Hash, path: kept.to_path_buf() });
+                    continue;
+                }
+                Err(e) => {
+                    errors.push(OrganizerError::io(Operation::Hash, kept, e));
+                    continue;
+                }
+            }
+
+            for duplicate in group.extras(&keep) {
+                match Self::resolve_duplicate(duplicate, kept, group, action, &report.root, &quarantine, &mut journal) {
+                    Ok(()) => {
+                        resolved += 1;
+                        bytes += group.size;
+                    }
+                    // Sin diario no se sigue: lo que viniera después no se podría deshacer
+                    Err(e) if e.operation() == Some(Operation::WriteJournal) => {
+                        errors.push(e);
+                        break 'groups;
+                    }
+                    Err(e) => errors.push(e),
+                }
             }
         }
 
-        // Contadores y estadísticas
-        let mut moved_files = 0;
-        let mut created_folders = 0;
-        let mut extension_map: HashMap<String, Vec<String>> = HashMap::new();
-        let mut errors = Vec::new();
+        let run_id = journal.finish().unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
 
-        // Procesar todos los archivos encontrados
-        for (idx, file_path) in all_files.iter().enumerate() {
-            // Reportar progreso
-            progress_callback(idx + 1, all_files.len());
+        let action_text = match action {
+            DuplicateAction::Delete => "Duplicados borrados",
+            DuplicateAction::HardLink => "Duplicados sustituidos por enlaces duros",
+            DuplicateAction::SymLink => "Duplicados sustituidos por enlaces simbolicos",
+            DuplicateAction::Quarantine => "Duplicados movidos a Duplicates/",
+        };
+        let mut summary = format!("{}: {}\n", action_text, resolved);
+        if action.frees_space() {
+            summary.push_str(&format!("Espacio liberado: {}\n", format_size(bytes)));
+        }
+        summary.push_str(&format!("Errores: {}\n", errors.len()));
+
+        Ok(DuplicateResolutionResult {
+            run_id,
+            resolved,
+            bytes,
+            summary,
+            errors: if errors.is_empty() { None } else { Some(errors) },
+        })
+    }
+
+    /// Aplica la acción a una copia sobrante y la anota en el diario
+    fn resolve_duplicate(
+        duplicate: &Path,
+        kept: &Path,
+        group: &DuplicateGroup,
+        action: DuplicateAction,
+        root: &Path,
+        quarantine: &Path,
+        journal: &mut JournalWriter,
+    ) -> Result<(), OrganizerError> {
+        let fail = |operation| move |e| OrganizerError::io(operation, duplicate, e);
 
-            // Obtener extensión del archivo
-            let extension = if let Some(ext) = file_path.extension() {
-                ext.to_string_lossy().to_lowercase().to_string()
-            } else {
-                "sin_extension".to_string()
+        // El contenido se comprueba justo antes de tocar nada
+        let hash = transfer::hash_file(duplicate).map_err(fail(Operation::Hash))?;
+        if hash.to_hex().as_str() != group.hash {
+            let operation = match action {
+                DuplicateAction::Quarantine => Operation::Move,
+                DuplicateAction::Delete => Operation::Delete,
+                DuplicateAction::HardLink | DuplicateAction::SymLink => Operation::Link,
             };
+            return Err(OrganizerError::Changed { operation, path: duplicate.to_path_buf() });
+        }
+        let modified = fs::metadata(duplicate).and_then(|m| m.modified()).ok();
+
+        let entry_action = match action {
+            DuplicateAction::Quarantine => {
+                let relative = duplicate.strip_prefix(root).unwrap_or(duplicate);
+                let destination = conflict::unique_path(&quarantine.join(relative));
+                if let Some(parent) = destination.parent() {
+                    fs::create_dir_all(parent).map_err(|e| OrganizerError::io(Operation::CreateDir, parent, e))?;
+                }
+                let link_target = fs::read_link(duplicate).ok();
+                transfer::move_file(duplicate, &destination, false).map_err(fail(Operation::Move))?;
+                return journal.record_link(duplicate, &destination, group.size, link_target.as_deref());
+            }
+            DuplicateAction::Delete => {
+                fs::remove_file(duplicate).map_err(fail(Operation::Delete))?;
+                EntryAction::Deleted
+            }
+            DuplicateAction::HardLink => {
+                transfer::replace_with_link(duplicate, kept, false).map_err(fail(Operation::Link))?;
+                EntryAction::HardLinked
+            }
+            DuplicateAction::SymLink => {
+                transfer::replace_with_link(duplicate, kept, true).map_err(fail(Operation::Link))?;
+                EntryAction::SymLinked
+            }
+        };
+
+        journal.record_replaced(duplicate, kept, group.size, entry_action, &group.hash, modified)
+    }
+
+    /// Comprueba que `path` exista y sea una carpeta
+    fn check_dir(path: &Path) -> Result<(), OrganizerError> {
+        if !path.exists() {
+            return Err(OrganizerError::NotFound { path: path.to_path_buf() });
+        }
+        if !path.is_dir() {
+            return Err(OrganizerError::NotADirectory { path: path.to_path_buf() });
+        }
+        Ok(())
+    }
+
+    /// Carpeta raíz de destino para organizar `path`
+    pub fn output_root(path: &Path, output_dir: Option<&Path>) -> PathBuf {
+        output_dir
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| path.join(DEFAULT_OUTPUT_DIR))
+    }
+
+    /// Reúne lo que las estrategias necesitan saber de un archivo e indica si
+    /// la extensión no corresponde con el contenido detectado
+    fn file_entry(root: &Path, file: ScannedFile, type_detection: TypeDetection) -> (FileEntry, Option<TypeMismatch>) {
+        let ScannedFile { path: file_path, metadata } = file;
+        let file_path = file_path.as_path();
+
+        let file_name = file_path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "archivo".to_string());
 
-            // Crear nombre de carpeta para la extensión
-            let folder_name = if extension == "sin_extension" {
-                "Sin_Extension".to_string()
-            } else {
-                extension.to_uppercase()
+        // Obtener extensión del archivo
+        let extension = file_path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+
+        let detected = match type_detection {
+            TypeDetection::Off => None,
+            _ => detect_file_type(file_path).ok().flatten(),
+        };
+
+        let mismatch = match (&extension, detected) {
+            (Some(ext), Some(detected)) if !detected.accepts_extension(ext) => Some(TypeMismatch {
+                path: file_path.to_path_buf(),
+                extension: ext.clone(),
+                detected,
+            }),
+            _ => None,
+        };
+
+        // El contenido sustituye a la extensión si no la hay o si así se pidió
+        let use_detected = detected.is_some()
+            && (extension.is_none()
+                || (mismatch.is_some() && type_detection == TypeDetection::ContentFirst));
+
+        let (extension, mime_type) = match detected {
+            Some(detected) if use_detected => (Some(detected.extension.to_string()), Some(detected.mime_type)),
+            Some(detected) if mismatch.is_none() => (extension, Some(detected.mime_type)),
+            _ => {
+                let mime_type = extension.as_deref().and_then(mime_from_extension);
+                (extension, mime_type)
+            }
+        };
+
+        let entry = FileEntry {
+            path: file_path.to_path_buf(),
+            relative_path: file_path.strip_prefix(root).unwrap_or(file_path).to_path_buf(),
+            file_name,
+            extension,
+            mime_type,
+            detected_type: detected,
+            extension_from_content: use_detected,
+            metadata,
+        };
+        (entry, mismatch)
+    }
+
+    /// Aplica un plan calculado previamente con `plan_organize`
+    pub fn execute_plan(plan: &OrganizePlan, progress_callback: &ProgressCallback<'_>) -> Result<OrganizerResult, OrganizerError> {
+        // Sin nadie a quien preguntar, los conflictos con `Ask` se omiten
+        Self::execute_plan_with_resolver(plan, progress_callback, |_| ConflictPolicy::Skip)
+    }
+
+    /// Aplica un plan; `resolver` decide los conflictos cuando la política es `Ask`
+    pub fn execute_plan_with_resolver<R>(
+        plan: &OrganizePlan,
+        progress_callback: &ProgressCallback<'_>,
+        resolver: R,
+    ) -> Result<OrganizerResult, OrganizerError>
+    where
+        R: FnMut(&PlannedOperation) -> ConflictPolicy,
+    {
+        Self::execute_plan_with_observer(plan, progress_callback, resolver, |_| {})
+    }
+
+    /// Aplica un plan avisando a `observer` en cuanto termina cada archivo
+    pub fn execute_plan_with_observer<R, O>(
+        plan: &OrganizePlan,
+        progress_callback: &ProgressCallback<'_>,
+        resolver: R,
+        observer: O,
+    ) -> Result<OrganizerResult, OrganizerError>
+    where
+        R: FnMut(&PlannedOperation) -> ConflictPolicy,
+        O: FnMut(&FileOutcome),
+    {
+        if plan.is_empty() {
+            return Ok(OrganizerResult {
+                type_mismatches: plan.type_mismatches.clone(),
+                skipped: plan.skipped.clone(),
+                ignored: plan.ignored.clone(),
+                filtered: plan.filtered.clone(),
+                unreadable: plan.unreadable.clone(),
+                cancelled: plan.cancelled,
+                ..OrganizerResult::empty()
+            });
+        }
+
+        let mut feed = PlanFeed::new(plan);
+        // El diario existe antes de la primera transferencia; crea la carpeta de
+        // destino y la vuelve a quitar si la ejecución no llega a hacer nada
+        let journal = JournalWriter::create(&plan.organizer_path, &plan.root, plan.mode)?;
+        let run = Self::run_feed(plan, journal, &mut feed, progress_callback, resolver, observer);
+        Ok(Self::build_result(plan, run, plan.operations.len()))
+    }
+
+    /// Ejecuta las operaciones según las va dando `feed`
+    fn run_feed<F, R, O>(
+        plan: &OrganizePlan,
+        journal: JournalWriter,
+        feed: &mut F,
+        progress_callback: &ProgressCallback<'_>,
+        mut resolver: R,
+        mut observer: O,
+    ) -> RunState
+    where
+        F: OperationFeed,
+        R: FnMut(&PlannedOperation) -> ConflictPolicy,
+        O: FnMut(&FileOutcome),
+    {
+        let mut run = RunState {
+            journal,
+            journal_error: None,
+            conflicts: Vec::new(),
+            created_folders: 0,
+            bytes_done: 0,
+            cancelled: false,
+            outcomes: Vec::new(),
+        };
+
+        // Los hilos solo transfieren; carpetas, conflictos, diario y avisos
+        // se hacen en este, en orden, así que no hay carreras entre archivos
+        let workers = plan.jobs.max(1);
+        let (job_sender, job_receiver) = mpsc::channel::<(usize, PathBuf, PathBuf)>();
+        let (done_sender, done_receiver) = mpsc::channel();
+        let job_receiver = Mutex::new(job_receiver);
+        let started = Instant::now();
+        thread::scope(|scope| {
+            if workers > 1 {
+                for _ in 0..workers {
+                    let job_receiver = &job_receiver;
+                    let done_sender = done_sender.clone();
+                    scope.spawn(move || {
+                        loop {
+                            let job = match job_receiver.lock() {
+                                Ok(receiver) => receiver.recv(),
+                                Err(_) => break,
+                            };
+                            let Ok((idx, source, destination)) = job else {
+                                break;
+                            };
+                            // Un pánico en una transferencia cuenta como un fallo de ese archivo
+                            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                                Self::transfer_operation(plan, &source, &destination)
+                            }))
+                            .unwrap_or_else(|_| Err(Self::worker_lost()));
+                            if done_sender.send((idx, result)).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            }
+            drop(done_sender);
+
+            // Transferencias en curso en los hilos
+            let mut pending: HashMap<usize, (PlannedOperation, PreparedOperation)> = HashMap::new();
+            let wait_one = |run: &mut RunState,
+                            pending: &mut HashMap<usize, (PlannedOperation, PreparedOperation)>,
+                            observer: &mut O| {
+                match done_receiver.recv() {
+                    Ok((idx, result)) => {
+                        if let Some((operation, prepared)) = pending.remove(&idx) {
+                            let outcome = Self::finish_operation(plan, &operation, prepared, result, run);
+                            run.finish(idx, &operation, outcome, observer);
+                        }
+                    }
+                    // Sin hilos no va a terminar nada más: lo que quedaba se da por fallido
+                    Err(_) => {
+                        let mut lost: Vec<_> = pending.drain().collect();
+                        lost.sort_by_key(|(idx, _)| *idx);
+                        for (idx, (operation, prepared)) in lost {
+                            let outcome = Self::finish_operation(plan, &operation, prepared, Err(Self::worker_lost()), run);
+                            run.finish(idx, &operation, outcome, observer);
+                        }
+                    }
+                }
             };
 
-            // Crear carpeta para la extensión si no existe
-            let extension_folder = organizer_path.join(&folder_name);
-            if !extension_folder.exists() {
-                if let Err(e) = fs::create_dir(&extension_folder) {
-                    errors.push(format!("Error al crear carpeta {}: {}", folder_name, e));
+            loop {
+                // Solo se para entre archivos; el que se está copiando se interrumpe en `transfer`
+                if run.cancelled || plan.cancel.is_cancelled() {
+                    run.cancelled = true;
+                    break;
+                }
+                if run.journal_error.is_some() {
+                    break;
+                }
+                let Some(operation) = feed.next_operation() else {
+                    break;
+                };
+                let idx = run.outcomes.len();
+                run.outcomes.push(None);
+
+                // Un conflicto se resuelve con todo lo anterior ya en disco, como en una
+                // ejecución secuencial; si no, dos archivos podrían elegir el mismo nombre
+                if operation.has_conflicts() || operation.destination.exists() {
+                    while !pending.is_empty() {
+                        wait_one(&mut run, &mut pending, &mut observer);
+                    }
+                }
+
+                // Reportar progreso
+                progress_callback(&Progress {
+                    current: idx + 1,
+                    total: feed.total_files(),
+                    file: operation.source.clone(),
+                    bytes_done: run.bytes_done,
+                    bytes_total: feed.total_bytes(),
+                    elapsed: started.elapsed(),
+                    estimated: feed.is_estimate(),
+                });
+
+                let prepared = match Self::prepare_operation(plan, &operation, &mut resolver, &mut run) {
+                    Prepared::Ready(prepared) => prepared,
+                    Prepared::Finished(outcome) => {
+                        run.finish(idx, &operation, outcome, &mut observer);
+                        continue;
+                    }
+                };
+
+                // Los archivos renombrados por un conflicto se transfieren aquí mismo,
+                // para que el nombre elegido exista antes de mirar el siguiente
+                if workers == 1 || prepared.outcome.conflict.is_some() {
+                    let result = Self::transfer_operation(plan, &operation.source, &prepared.outcome.destination);
+                    let outcome = Self::finish_operation(plan, &operation, prepared, result, &mut run);
+                    run.finish(idx, &operation, outcome, &mut observer);
                     continue;
                 }
-                created_folders += 1;
+
+                if pending.len() >= workers {
+                    wait_one(&mut run, &mut pending, &mut observer);
+                }
+                let job = (idx, operation.source.clone(), prepared.outcome.destination.clone());
+                pending.insert(idx, (operation, prepared));
+                if job_sender.send(job).is_err() {
+                    break;
+                }
+            }
+
+            drop(job_sender);
+            while !pending.is_empty() {
+                wait_one(&mut run, &mut pending, &mut observer);
             }
+        });
+
+        run
+    }
+
+    /// Error de un archivo cuya transferencia no terminó porque su hilo se perdió
+    fn worker_lost() -> io::Error {
+        io::Error::other("la transferencia se interrumpió por un fallo interno")
+    }
+
+    /// Guarda el diario y arma el resultado de una ejecución; `total` son los
+    /// archivos que había que procesar, para el resumen si se canceló
+    fn build_result(plan: &OrganizePlan, run: RunState, total: usize) -> OrganizerResult {
+        let RunState { journal, journal_error, conflicts, created_folders, cancelled, outcomes, .. } = run;
+        let outcomes: Vec<FileOutcome> = outcomes.into_iter().flatten().collect();
+        let mut errors: Vec<OrganizerError> = journal_error.into_iter().collect();
 
-            // Move or copy file to the corresponding folder
-            let file_name = file_path.file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_else(|| "archivo".to_string());
+        // Cerrar el diario, que ya tiene en disco cada archivo según terminó
+        let run_id = journal.finish().unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
 
-            let new_path = extension_folder.join(&file_name);
+        // El resumen y el mapa por carpeta salen de los registros de cada archivo
+        let summary = Self::create_organization_summary(
+            &outcomes,
+            created_folders,
+            plan.ignored.len(),
+            plan.filtered.len(),
+            plan.unreadable.len(),
+            plan.mode,
+            cancelled.then_some(total),
+        );
 
-            // Perform the operation based on mode
-            let operation_result = match mode {
-                FileOperationMode::Cut => fs::rename(file_path, &new_path),
-                FileOperationMode::Copy => fs::copy(file_path, &new_path).map(|_| ()),
+        OrganizerResult {
+            total_moved: outcomes.iter().filter(|o| o.is_done()).count(),
+            folders_created: created_folders,
+            extension_map: extension_map(&outcomes),
+            outcomes,
+            summary,
+            errors: if errors.is_empty() { None } else { Some(errors) },
+            run_id,
+            conflicts,
+            type_mismatches: plan.type_mismatches.clone(),
+            skipped: plan.skipped.clone(),
+            ignored: plan.ignored.clone(),
+            filtered: plan.filtered.clone(),
+            unreadable: plan.unreadable.clone(),
+            cancelled,
+        }
+    }
+
+    /// Crea la carpeta de un archivo del plan y resuelve su conflicto, si lo hay
+    fn prepare_operation<R>(
+        plan: &OrganizePlan,
+        operation: &PlannedOperation,
+        resolver: &mut R,
+        run: &mut RunState,
+    ) -> Prepared
+    where
+        R: FnMut(&PlannedOperation) -> ConflictPolicy,
+    {
+        let started = Instant::now();
+        let mut outcome = FileOutcome {
+            source: operation.source.clone(),
+            destination: operation.destination.clone(),
+            folder: operation.folder_name.clone(),
+            action: operation.action,
+            bytes: 0,
+            duration: Duration::ZERO,
+            status: OutcomeStatus::Skipped,
+            conflict: None,
+            error: None,
+        };
+
+        // Crear carpeta de destino si no existe
+        let mut created_folder = false;
+        if let Some(folder) = operation.destination.parent()
+            && !folder.exists()
+        {
+            if let Err(e) = fs::create_dir_all(folder) {
+                outcome.status = OutcomeStatus::Failed;
+                outcome.error = Some(OrganizerError::io(Operation::CreateDir, folder, e));
+                outcome.duration = started.elapsed();
+                return Prepared::Finished(outcome);
+            }
+            run.created_folders += 1;
+            created_folder = true;
+        }
+
+        // Resolver el conflicto si el destino ya existe
+        if outcome.destination.exists() {
+            let policy = match plan.conflict_policy {
+                ConflictPolicy::Ask => resolver(operation),
+                policy => policy,
             };
+            let (target, resolution) = conflict::resolve(&operation.source, &outcome.destination, policy);
+            run.conflicts.push(ConflictRecord {
+                source: operation.source.clone(),
+                destination: outcome.destination.clone(),
+                policy,
+                resolution: resolution.clone(),
+            });
+            outcome.conflict = Some(resolution);
 
-            match operation_result {
-                Ok(_) => {
-                    moved_files += 1;
-                    extension_map
-                        .entry(folder_name.clone())
-                        .or_insert_with(Vec::new)
-                        .push(file_name);
+            match target {
+                Some(target) => outcome.destination = target,
+                None => {
+                    outcome.duration = started.elapsed();
+                    return Prepared::Finished(outcome);
                 }
+            }
+        }
+
+        // Lo que se va a sobrescribir se aparta en lugar de perderse; queda en el
+        // diario junto al archivo que lo sustituye y vuelve a su sitio al deshacer
+        let mut set_aside = None;
+        if let Ok(existing) = fs::symlink_metadata(&outcome.destination)
+            && !existing.is_dir()
+        {
+            match run.journal.set_aside(&outcome.destination) {
+                Ok(aside) => set_aside = Some((aside, existing.len())),
                 Err(e) => {
-                    errors.push(format!("Error al mover {}: {}", file_name, e));
+                    outcome.status = OutcomeStatus::Failed;
+                    outcome.error = Some(e);
+                    outcome.duration = started.elapsed();
+                    return Prepared::Finished(outcome);
                 }
             }
         }
 
-        // Create summary of organization
-        let summary = Self::create_organization_summary(&extension_map, moved_files, created_folders, mode);
+        let link_target = fs::read_link(&operation.source).ok();
+        Prepared::Ready(PreparedOperation { outcome, started, created_folder, link_target, set_aside })
+    }
+
+    /// Mueve o copia un archivo ya preparado; es lo único que corre en los hilos
+    fn transfer_operation(plan: &OrganizePlan, source: &Path, destination: &Path) -> io::Result<u64> {
+        match plan.mode {
+            FileOperationMode::Cut => transfer::move_file_with_cancel(source, destination, plan.verify_transfers, &plan.cancel),
+            FileOperationMode::Copy => transfer::copy_file_with_cancel(source, destination, plan.verify_transfers, &plan.cancel),
+        }
+    }
+
+    /// Registra lo que pasó con la transferencia de un archivo
+    fn finish_operation(
+        plan: &OrganizePlan,
+        operation: &PlannedOperation,
+        prepared: PreparedOperation,
+        result: io::Result<u64>,
+        run: &mut RunState,
+    ) -> FileOutcome {
+        let PreparedOperation { mut outcome, started, created_folder, link_target, set_aside } = prepared;
 
-        Ok(OrganizerResult {
-            total_moved: moved_files,
-            folders_created: created_folders,
-            extension_map,
+        match result {
+            Ok(size) => {
+                // El apartado va antes en el diario: al deshacer vuelve después de quitar su sustituto
+                let recorded = set_aside
+                    .map_or(Ok(()), |(aside, size)| run.journal.record_set_aside(&outcome.destination, &aside, size))
+                    .and_then(|_| run.journal.record_link(&operation.source, &outcome.destination, size, link_target.as_deref()));
+                if let Err(e) = recorded {
+                    run.journal_error.get_or_insert(e);
+                }
+                outcome.status = OutcomeStatus::Done;
+                outcome.bytes = size;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted && plan.cancel.is_cancelled() => {
+                Self::put_back(run, set_aside, &outcome.destination);
+                // La copia a medias ya se borró; tampoco se deja la carpeta creada para ella
+                if created_folder && Self::remove_empty_parents(&outcome.destination, &plan.organizer_path) {
+                    run.created_folders -= 1;
+                }
+                outcome.status = OutcomeStatus::Cancelled;
+            }
+            Err(e) => {
+                Self::put_back(run, set_aside, &outcome.destination);
+                let failed = match operation.action {
+                    FileOperationMode::Cut => Operation::Move,
+                    FileOperationMode::Copy => Operation::Copy,
+                };
+                outcome.status = OutcomeStatus::Failed;
+                outcome.error = Some(OrganizerError::io(failed, &operation.source, e));
+            }
+        }
+        outcome.duration = started.elapsed();
+        outcome
+    }
+
+    /// Devuelve a su sitio lo que se apartó para una transferencia que no se hizo.
+    /// Si no se puede, se anota en el diario para recuperarlo al deshacer.
+    fn put_back(run: &mut RunState, set_aside: Option<(PathBuf, u64)>, destination: &Path) {
+        let Some((aside, size)) = set_aside else {
+            return;
+        };
+        let put_back = run.journal.put_back(&aside, destination);
+        if put_back.is_err()
+            && let Err(e) = run.journal.record_set_aside(destination, &aside, size)
+        {
+            run.journal_error.get_or_insert(e);
+        }
+    }
+
+    /// Deshace una ejecución anterior a partir de su diario (la más reciente si no se indica `run_id`)
+    pub fn undo(path: &str, run_id: Option<&str>) -> Result<UndoResult, OrganizerError> {
+        Self::undo_with_output(path, None, run_id)
+    }
+
+    /// Revierte una ejecución que usó `output_dir` como carpeta de destino
+    pub fn undo_with_output(path: &str, output_dir: Option<&Path>, run_id: Option<&str>) -> Result<UndoResult, OrganizerError> {
+        Self::undo_with_progress(path, output_dir, run_id, &|_| {}, &CancelToken::new())
+    }
+
+    /// Como `undo_with_output`, avisando a `progress_callback` antes de cada
+    /// entrada del diario. Si se cancela, para entre entradas y las que faltan
+    /// siguen en el diario para deshacerlas más tarde.
+    pub fn undo_with_progress(
+        path: &str,
+        output_dir: Option<&Path>,
+        run_id: Option<&str>,
+        progress_callback: &ProgressCallback<'_>,
+        cancel: &CancelToken,
+    ) -> Result<UndoResult, OrganizerError> {
+        let path = Path::new(path);
+
+        Self::check_dir(path)?;
+
+        let organizer_path = Self::output_root(path, output_dir);
+        let mut journal = match run_id {
+            Some(run_id) => RunJournal::load(&organizer_path, run_id)?,
+            None => RunJournal::list(&organizer_path)?
+                .pop()
+                .ok_or(OrganizerError::NothingToUndo)?,
+        };
+
+        let mut restored = 0;
+        let mut recovered = 0;
+        let mut errors = Vec::new();
+        let mut pending = Vec::new();
+        let mut skipped = 0;
+
+        let total = journal.entries.len();
+        let bytes_total = journal.entries.iter().map(|entry| entry.size).sum();
+        let mut bytes_done = 0;
+        let started = Instant::now();
+
+        // Se recorre en orden inverso para deshacer primero lo último que se hizo:
+        // un archivo sobrescrito vuelve después de quitar la copia que lo sustituyó
+        for (done, entry) in journal.entries.iter().rev().enumerate() {
+            if skipped > 0 || cancel.is_cancelled() {
+                skipped += 1;
+                pending.push(entry.clone());
+                continue;
+            }
+            progress_callback(&Progress {
+                current: done + 1,
+                total,
+                file: entry.new_path.clone(),
+                bytes_done,
+                bytes_total,
+                elapsed: started.elapsed(),
+                estimated: false,
+            });
+            bytes_done += entry.size;
+
+            match Self::undo_entry(entry, journal.mode) {
+                Ok(_) if entry.action == EntryAction::Replaced => recovered += 1,
+                Ok(_) => restored += 1,
+                Err(e) => {
+                    errors.push(e);
+                    pending.push(entry.clone());
+                }
+            }
+            Self::remove_empty_parents(&entry.new_path, &organizer_path);
+        }
+
+        // El diario solo se conserva con las entradas que no se pudieron deshacer
+        if pending.is_empty() {
+            journal.remove(&organizer_path)?;
+        } else {
+            pending.reverse();
+            journal.entries = pending;
+            journal.save(&organizer_path)?;
+        }
+        // Una carpeta de destino elegida por el usuario se deja aunque quede vacía
+        if output_dir.is_none() {
+            let _ = fs::remove_dir(&organizer_path);
+        }
+
+        let operation_name = match journal.mode {
+            FileOperationMode::Cut => "restaurados",
+            FileOperationMode::Copy => "eliminados (copias)",
+        };
+        let status = if skipped > 0 {
+            format!("cancelada: quedan {} por deshacer", skipped)
+        } else {
+            "deshecha".to_string()
+        };
+        let summary = format!(
+            "Ejecucion {} {}\n\nArchivos {}: {}\nArchivos sobrescritos recuperados: {}\nErrores: {}\n",
+            journal.run_id, status, operation_name, restored, recovered, errors.len()
+        );
+
+        Ok(UndoResult {
+            run_id: journal.run_id,
+            restored,
+            recovered,
+            cancelled: skipped > 0,
             summary,
             errors: if errors.is_empty() { None } else { Some(errors) },
         })
     }
 
-    /// Recursively collects all files in a directory and subdirectories, excluding specified items
-    fn collect_files_recursive(
-        dir_path: &Path,
-        excluded_items: &[String],
-        files: &mut Vec<std::path::PathBuf>,
-    ) -> Result<(), String> {
-        match fs::read_dir(dir_path) {
-            Ok(entries) => {
-                for entry in entries {
-                    match entry {
-                        Ok(entry) => {
-                            let path = entry.path();
-                            
-                            // Check if the item should be excluded
-                            if let Some(item_name) = path.file_name() {
-                                let item_name_lower = item_name.to_string_lossy().to_lowercase();
-                                
-                                // Skip if it's the Organizer folder or in the exclusion list
-                                if item_name_lower == "organizer" || excluded_items.contains(&item_name_lower) {
-                                    continue;
-                                }
-                            }
+    /// Deshace una entrada del diario: devuelve el archivo a su sitio, borra la
+    /// copia o vuelve a crear el duplicado que se sustituyó
+    fn undo_entry(entry: &JournalEntry, mode: FileOperationMode) -> Result<(), OrganizerError> {
+        match entry.action {
+            EntryAction::Transfer => {}
+            EntryAction::Replaced => return Self::restore_set_aside(entry),
+            EntryAction::Deleted | EntryAction::HardLinked | EntryAction::SymLinked => {
+                return Self::restore_duplicate(entry);
+            }
+        }
 
-                            if path.is_file() {
-                                files.push(path);
-                            } else if path.is_dir() {
-                                // Recursively collect files from subdirectories
-                                Self::collect_files_recursive(&path, excluded_items, files)?;
-                            }
-                        }
-                        Err(e) => {
-                            return Err(format!("Error leyendo entrada: {}", e));
-                        }
-                    }
+        let new_path = &entry.new_path;
+        let original_path = &entry.original_path;
+
+        // Sin seguir enlaces: el archivo trasladado puede ser un enlace simbólico
+        if fs::symlink_metadata(new_path).is_err() {
+            return Err(OrganizerError::NotFound { path: new_path.clone() });
+        }
+
+        match mode {
+            FileOperationMode::Cut => {
+                if fs::symlink_metadata(original_path).is_ok() {
+                    return Err(OrganizerError::Collision { operation: Operation::Restore, path: original_path.clone() });
                 }
+
+                // Recrear las subcarpetas de origen que hayan quedado vacías y borradas
+                if let Some(parent) = original_path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| OrganizerError::io(Operation::CreateDir, parent, e))?;
+                }
+
+                // Un enlace vuelve con el destino exacto que tenía, relativo o no
+                match &entry.link_target {
+                    Some(target) => transfer::move_link(new_path, original_path, target),
+                    None => transfer::move_file(new_path, original_path, false).map(|_| ()),
+                }
+                .map_err(|e| OrganizerError::io(Operation::Restore, original_path, e))
+            }
+            FileOperationMode::Copy => {
+                // Si la copia sobrescribió un archivo, este vuelve con su propia entrada
+                // (`Replaced`), que está antes en el diario y se deshace después.
+                // No se borran copias que se hayan modificado después de la ejecución.
+                let size = fs::symlink_metadata(new_path).map(|m| m.len()).unwrap_or(0);
+                if size != entry.size {
+                    return Err(OrganizerError::Changed { operation: Operation::Delete, path: new_path.clone() });
+                }
+
+                fs::remove_file(new_path)
+                    .map_err(|e| OrganizerError::io(Operation::Delete, new_path, e))
+            }
+        }
+    }
+
+    /// Devuelve a su sitio un archivo que se apartó para que otro lo sustituyera,
+    /// una vez que el sustituto ya se quitó
+    fn restore_set_aside(entry: &JournalEntry) -> Result<(), OrganizerError> {
+        let aside = &entry.new_path;
+        let original_path = &entry.original_path;
+
+        if fs::symlink_metadata(aside).is_err() {
+            return Err(OrganizerError::NotFound { path: aside.clone() });
+        }
+        if fs::symlink_metadata(original_path).is_ok() {
+            return Err(OrganizerError::Collision { operation: Operation::Restore, path: original_path.clone() });
+        }
+
+        if let Some(parent) = original_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| OrganizerError::io(Operation::CreateDir, parent, e))?;
+        }
+        transfer::move_file(aside, original_path, false)
+            .map(|_| ())
+            .map_err(|e| OrganizerError::io(Operation::Restore, original_path, e))
+    }
+
+    /// Vuelve a crear un duplicado borrado o sustituido por un enlace a partir
+    /// de la copia que se conservó
+    fn restore_duplicate(entry: &JournalEntry) -> Result<(), OrganizerError> {
+        let kept = &entry.new_path;
+        let original_path = &entry.original_path;
+
+        // Solo se reemplaza lo que dejó la ejecución: nada o el enlace que creó
+        let current = fs::symlink_metadata(original_path);
+        let untouched = match entry.action {
+            EntryAction::Deleted => current.is_err(),
+            EntryAction::HardLinked => current.is_ok_and(|m| m.is_file()),
+            EntryAction::SymLinked => {
+                current.is_ok_and(|m| m.is_symlink())
+                    && fs::canonicalize(original_path).ok() == fs::canonicalize(kept).ok()
             }
-            Err(e) => return Err(format!("Error al leer el directorio: {}", e)),
+            EntryAction::Transfer | EntryAction::Replaced => false,
+        };
+        if !untouched {
+            return Err(OrganizerError::Changed { operation: Operation::Restore, path: original_path.clone() });
+        }
+
+        let hash = transfer::hash_file(kept)
+            .map_err(|e| OrganizerError::io(Operation::Hash, kept, e))?;
+        if entry.hash.as_deref().is_some_and(|expected| hash.to_hex().as_str() != expected) {
+            return Err(OrganizerError::Changed { operation: Operation::Restore, path: kept.clone() });
         }
 
+        if let Some(parent) = original_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| OrganizerError::io(Operation::CreateDir, parent, e))?;
+        }
+        transfer::copy_file(kept, original_path, false)
+            .map_err(|e| OrganizerError::io(Operation::Restore, original_path, e))?;
+
+        if let Some(modified) = entry.modified
+            && let Ok(file) = fs::File::options().write(true).open(original_path)
+        {
+            let _ = file.set_modified(modified);
+        }
         Ok(())
     }
 
+    /// Borra las carpetas vacías entre `file_path` y `organizer_path`. Indica si
+    /// se borró la carpeta del archivo, que no lo está si otro archivo llegó a ella
+    fn remove_empty_parents(file_path: &Path, organizer_path: &Path) -> bool {
+        let mut removed_own = false;
+        let mut current = file_path.parent();
+        while let Some(dir) = current {
+            if dir == organizer_path || !dir.starts_with(organizer_path) {
+                break;
+            }
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+            removed_own = true;
+            current = dir.parent();
+        }
+        removed_own
+    }
+
     /// Crea un resumen de la organización
     fn create_organization_summary(
-        extension_map: &HashMap<String, Vec<String>>,
-        total_moved: usize,
+        outcomes: &[FileOutcome],
         folders_created: usize,
+        ignored: usize,
+        filtered: usize,
+        unreadable: usize,
         mode: FileOperationMode,
+        cancelled_of: Option<usize>,
     ) -> String {
         let operation_name = match mode {
             FileOperationMode::Cut => "movidos",
             FileOperationMode::Copy => "copiados",
         };
-        
-        let mut summary = format!(
-            "Organizacion completada\n\n\
-             Archivos {}: {}\n\
-             Carpetas creadas: {}\n\n\
+        let count = |status| outcomes.iter().filter(|o| o.status == status).count();
+        let bytes: u64 = outcomes.iter().filter(|o| o.is_done()).map(|o| o.bytes).sum();
+        let conflicts = outcomes.iter().filter(|o| o.conflict.is_some()).count();
+
+        // Si se canceló, cuántos de los archivos planificados se llegaron a procesar
+        let mut summary = match cancelled_of {
+            Some(total) => format!(
+                "Organizacion cancelada: {} de {} archivos procesados\n\n",
+                outcomes.len() - count(OutcomeStatus::Cancelled),
+                total
+            ),
+            None => "Organizacion completada\n\n".to_string(),
+        };
+        summary.push_str(&format!(
+            "Archivos {}: {} ({})\n\
+             Archivos omitidos: {}\n\
+             Archivos con errores: {}\n\
+             Archivos cancelados: {}\n\
+             Carpetas creadas: {}\n\
+             Conflictos resueltos: {}\n\
+             Ignorados por .organizerignore: {}\n\
+             Descartados por los filtros: {}\n\
+             Rutas sin leer: {}\n\n\
              Extensiones organizadas:\n",
-            operation_name, total_moved, folders_created
-        );
-        
-        for (extension, files) in extension_map {
+            operation_name,
+            count(OutcomeStatus::Done),
+            format_size(bytes),
+            count(OutcomeStatus::Skipped),
+            count(OutcomeStatus::Failed),
+            count(OutcomeStatus::Cancelled),
+            folders_created,
+            conflicts,
+            ignored,
+            filtered,
+            unreadable
+        ));
+
+        for (extension, files) in extension_map(outcomes) {
             summary.push_str(&format!("  - {}: {} archivo(s)\n", extension, files.len()));
         }
         
@@ -247,7 +1508,7 @@ impl FileOrganizerCore {
     }
 
     /// Maneja cuando la ruta es un archivo individual
-    fn list_single_file(path: &Path) -> Result<Vec<String>, String> {
+    fn list_single_file(path: &Path) -> Result<Vec<String>, OrganizerError> {
         if let Some(file_name) = path.file_name() {
             let file_info = format!(
                 "Archivo {} ({} bytes)",
@@ -261,7 +1522,7 @@ impl FileOrganizerCore {
     }
 
     /// Lista archivos en un directorio
-    fn list_files_in_directory(path: &Path) -> Result<Vec<String>, String> {
+    fn list_files_in_directory(path: &Path) -> Result<Vec<String>, OrganizerError> {
         let mut files = Vec::new();
         
         match fs::read_dir(path) {
@@ -296,29 +1557,548 @@ impl FileOrganizerCore {
                 files.sort();
                 Ok(files)
             }
-            Err(e) => Err(format!("Error al leer el directorio: {}", e)),
+            Err(e) => Err(OrganizerError::io(Operation::ReadDir, path, e)),
         }
     }
 }
 
 /// Resultado de la operación de organización
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct OrganizerResult {
+    /// Archivos movidos o copiados, según `outcomes`
     pub total_moved: usize,
     pub folders_created: usize,
+    /// Archivos transferidos por carpeta, según `outcomes`
     pub extension_map: HashMap<String, Vec<String>>,
+    /// Qué pasó con cada archivo del plan, en el orden en que se procesó
+    pub outcomes: Vec<FileOutcome>,
     pub summary: String,
-    pub errors: Option<Vec<String>>,
+    /// Errores que no son de un archivo concreto (p. ej. al guardar el diario);
+    /// los de cada archivo están en `outcomes`
+    pub errors: Option<Vec<OrganizerError>>,
+    /// Identificador del diario de la ejecución, necesario para deshacerla
+    pub run_id: Option<String>,
+    /// Conflictos de nombre encontrados y cómo se resolvió cada uno
+    pub conflicts: Vec<ConflictRecord>,
+    /// Archivos cuya extensión no corresponde con su contenido
+    pub type_mismatches: Vec<TypeMismatch>,
+    /// Archivos que se quedan en su sitio: los que la estrategia no mueve, los
+    /// enlaces que no se siguen y lo que no es un archivo normal (sockets, FIFO...)
+    pub skipped: Vec<SkippedFile>,
+    /// Elementos que no se recorrieron por un `.organizerignore`
+    pub ignored: Vec<IgnoredPath>,
+    /// Archivos descartados por el filtro de tamaño, fecha, nombre u ocultos
+    pub filtered: Vec<PathBuf>,
+    /// Carpetas que no se pudieron recorrer
+    pub unreadable: Vec<UnreadablePath>,
+    /// La ejecución se detuvo antes de procesar todo el plan
+    pub cancelled: bool,
 }
 
 impl OrganizerResult {
+    /// Todos los errores: los de cada archivo y los generales
+    pub fn all_errors(&self) -> impl Iterator<Item = &OrganizerError> {
+        self.outcomes
+            .iter()
+            .filter_map(|outcome| outcome.error.as_ref())
+            .chain(self.errors.iter().flatten())
+    }
+
+    /// Si algún archivo falló, hubo algún error general o alguna carpeta no se pudo leer
+    pub fn has_errors(&self) -> bool {
+        self.all_errors().next().is_some() || !self.unreadable.is_empty()
+    }
+
     pub fn empty() -> Self {
         OrganizerResult {
             total_moved: 0,
             folders_created: 0,
             extension_map: HashMap::new(),
+            outcomes: Vec::new(),
             summary: "No files were processed.".to_string(),
             errors: None,
+            run_id: None,
+            conflicts: Vec::new(),
+            type_mismatches: Vec::new(),
+            skipped: Vec::new(),
+            ignored: Vec::new(),
+            filtered: Vec::new(),
+            unreadable: Vec::new(),
+            cancelled: false,
+        }
+    }
+}
+
+/// Resultado de resolver los duplicados de un informe
+#[derive(Debug, Serialize)]
+pub struct DuplicateResolutionResult {
+    /// Identificador del diario, necesario para deshacerlo
+    pub run_id: Option<String>,
+    /// Copias sobrantes tratadas
+    pub resolved: usize,
+    /// Bytes de esas copias
+    pub bytes: u64,
+    pub summary: String,
+    pub errors: Option<Vec<OrganizerError>>,
+}
+
+/// Resultado de deshacer una ejecución
+#[derive(Debug, Serialize)]
+pub struct UndoResult {
+    pub run_id: String,
+    pub restored: usize,
+    /// Archivos que la ejecución sobrescribió y volvieron a su sitio
+    pub recovered: usize,
+    /// Se canceló antes de terminar; lo que falta sigue en el diario
+    pub cancelled: bool,
+    pub summary: String,
+    pub errors: Option<Vec<OrganizerError>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::TempDir;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn text(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    fn path_str(path: &Path) -> &str {
+        path.to_str().unwrap()
+    }
+
+    /// Busca y resuelve los duplicados de `src/a.txt`, `src/sub/b.txt` y
+    /// `src/sub/c.txt`, comprueba el resultado con `check` y deshace la ejecución
+    fn duplicates_round_trip(test: &str, action: DuplicateAction, check: impl Fn(&Path, &Path)) {
+        let dir = TempDir::new(&format!("core-{}", test));
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        for name in ["a.txt", "sub/b.txt", "sub/c.txt"] {
+            write(&src.join(name), "igual");
         }
+        write(&src.join("otro.txt"), "distinto");
+
+        let report = FileOrganizerCore::find_duplicates(path_str(&src), &ScanOptions::recursive(), Some(&out)).unwrap();
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.duplicate_files(), 2);
+
+        let result = FileOrganizerCore::resolve_duplicates(&report, &KeepRule::ShortestPath, action, Some(&out)).unwrap();
+        assert!(result.errors.is_none(), "{:?}", result.errors);
+        assert_eq!(result.resolved, 2);
+        check(&src, &out);
+
+        let undo = FileOrganizerCore::undo_with_output(path_str(&src), Some(&out), result.run_id.as_deref()).unwrap();
+        assert!(undo.errors.is_none(), "{:?}", undo.errors);
+        assert_eq!(undo.restored, 2);
+        for name in ["a.txt", "sub/b.txt", "sub/c.txt"] {
+            let path = src.join(name);
+            assert!(fs::symlink_metadata(&path).unwrap().is_file(), "{}", path.display());
+            assert_eq!(text(&path), "igual");
+        }
+        assert_eq!(text(&src.join("otro.txt")), "distinto");
+        assert!(RunJournal::list(&out).unwrap().is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn delete_duplicates_and_undo() {
+        duplicates_round_trip("dupes-delete", DuplicateAction::Delete, |src, _| {
+            assert!(src.join("a.txt").exists());
+            assert!(!src.join("sub/b.txt").exists());
+            assert!(!src.join("sub/c.txt").exists());
+        });
+    }
+
+    #[test]
+    fn hardlink_duplicates_and_undo() {
+        duplicates_round_trip("dupes-hardlink", DuplicateAction::HardLink, |src, _| {
+            assert_eq!(text(&src.join("sub/b.txt")), "igual");
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                assert_eq!(fs::metadata(src.join("a.txt")).unwrap().nlink(), 3);
+            }
+        });
+    }
+
+    #[test]
+    fn symlink_duplicates_and_undo() {
+        duplicates_round_trip("dupes-symlink", DuplicateAction::SymLink, |src, _| {
+            for name in ["sub/b.txt", "sub/c.txt"] {
+                assert!(fs::symlink_metadata(src.join(name)).unwrap().is_symlink());
+                assert_eq!(fs::canonicalize(src.join(name)).unwrap(), fs::canonicalize(src.join("a.txt")).unwrap());
+            }
+        });
+    }
+
+    #[test]
+    fn quarantine_duplicates_and_undo() {
+        duplicates_round_trip("dupes-quarantine", DuplicateAction::Quarantine, |src, out| {
+            assert!(!src.join("sub/b.txt").exists());
+            assert_eq!(text(&out.join(QUARANTINE_DIR).join("sub/b.txt")), "igual");
+            assert_eq!(text(&out.join(QUARANTINE_DIR).join("sub/c.txt")), "igual");
+            // Lo apartado no vuelve a contar como duplicado
+            let report = FileOrganizerCore::find_duplicates(path_str(src), &ScanOptions::recursive(), Some(out)).unwrap();
+            assert!(report.groups.is_empty());
+        });
+    }
+
+    #[test]
+    fn duplicates_changed_after_the_scan_are_left_alone() {
+        let dir = TempDir::new("core-dupes-changed");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write(&src.join("a.txt"), "igual");
+        write(&src.join("sub/b.txt"), "igual");
+        let report = FileOrganizerCore::find_duplicates(path_str(&src), &ScanOptions::recursive(), Some(&out)).unwrap();
+        write(&src.join("sub/b.txt"), "cambio");
+
+        let result = FileOrganizerCore::resolve_duplicates(&report, &KeepRule::ShortestPath, DuplicateAction::Delete, Some(&out)).unwrap();
+        assert_eq!(result.resolved, 0);
+        assert!(matches!(result.errors.as_deref(), Some([OrganizerError::Changed { .. }])));
+        assert_eq!(text(&src.join("sub/b.txt")), "cambio");
+    }
+
+    #[test]
+    fn empty_parents_are_removed_only_when_nothing_else_arrived() {
+        let dir = TempDir::new("core-empty-parents");
+        let out = dir.join("out");
+        fs::create_dir_all(out.join("TXT/sub")).unwrap();
+        assert!(FileOrganizerCore::remove_empty_parents(&out.join("TXT/sub/a.txt"), &out));
+        assert!(!out.join("TXT").exists());
+
+        // Otro hilo ya dejó su archivo en la carpeta
+        write(&out.join("TXT/b.txt"), "b");
+        assert!(!FileOrganizerCore::remove_empty_parents(&out.join("TXT/a.txt"), &out));
+        assert!(out.join("TXT/b.txt").exists());
+    }
+
+    #[test]
+    fn cancelled_undo_keeps_the_rest_in_the_journal() {
+        let dir = TempDir::new("core-undo-cancel");
+        let src = dir.join("src");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            write(&src.join(name), name);
+        }
+        let result = FileOrganizerCore::organize_with_options(path_str(&src), &OrganizeOptions::default(), &|_| {}).unwrap();
+        assert_eq!(result.total_moved, 3);
+
+        // Se cancela en cuanto empieza la primera entrada: esa termina, el resto espera
+        let cancel = CancelToken::new();
+        let seen = std::sync::Mutex::new(Vec::new());
+        let undo = FileOrganizerCore::undo_with_progress(path_str(&src), None, None, &|progress| {
+            seen.lock().unwrap().push((progress.current, progress.total));
+            cancel.cancel();
+        }, &cancel).unwrap();
+        assert!(undo.cancelled);
+        assert_eq!(undo.restored, 1);
+        assert_eq!(*seen.lock().unwrap(), [(1, 3)]);
+        assert_eq!(RunJournal::list(&src.join("Organizer")).unwrap()[0].entries.len(), 2);
+
+        let undo = FileOrganizerCore::undo_with_output(path_str(&src), None, None).unwrap();
+        assert!(!undo.cancelled);
+        assert_eq!(undo.restored, 2);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert_eq!(text(&src.join(name)), name);
+        }
+        assert!(!src.join("Organizer").exists());
+    }
+
+    #[test]
+    fn duplicates_skip_the_output_folder() {
+        let dir = TempDir::new("core-dupes-output");
+        let src = dir.join("src");
+        write(&src.join("a.txt"), "igual");
+        write(&src.join("b.txt"), "igual");
+        let options = OrganizeOptions { mode: FileOperationMode::Copy, ..Default::default() };
+        let result = FileOrganizerCore::organize_with_options(path_str(&src), &options, &|_| {}).unwrap();
+        assert_eq!(result.total_moved, 2);
+
+        // Las copias de `Organizer/` no se ofrecen como duplicados de sus orígenes
+        let report = FileOrganizerCore::find_duplicates(path_str(&src), &ScanOptions::recursive(), None).unwrap();
+        assert_eq!(report.groups.len(), 1);
+        let mut files = report.groups[0].files.clone();
+        files.sort();
+        assert_eq!(files, [src.join("a.txt"), src.join("b.txt")]);
+
+        // Con una salida propia, lo que hay en ella tampoco cuenta
+        let out = src.join("sub");
+        write(&out.join("c.txt"), "igual");
+        let report = FileOrganizerCore::find_duplicates(path_str(&src), &ScanOptions::recursive(), Some(&out)).unwrap();
+        assert!(report.groups[0].files.iter().all(|file| !file.starts_with(&out)));
+    }
+
+    /// Organiza `src/a.txt` en `out/` cuando ya existe `out/TXT/a.txt`; el
+    /// archivo que se indica con `older` queda con una fecha anterior
+    fn run_with_conflict(test: &str, policy: ConflictPolicy, source: &str, existing: &str, older: &str) -> (TempDir, OrganizerResult) {
+        let dir = TempDir::new(&format!("core-{}", test));
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write(&src.join("a.txt"), source);
+        write(&out.join("TXT/a.txt"), existing);
+        let hour_ago = std::time::SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(dir.join(older)).unwrap().set_modified(hour_ago).unwrap();
+
+        let options = OrganizeOptions { output_dir: Some(out), conflict_policy: policy, ..Default::default() };
+        let plan = FileOrganizerCore::plan_organize(path_str(&src), &options).unwrap();
+        assert_eq!(plan.operations.len(), 1);
+        assert!(plan.operations[0].conflicts.contains(&PlanConflict::DestinationExists));
+        // `Ask` pregunta una sola vez y aplica lo que se responda
+        let mut asked = 0;
+        let result = FileOrganizerCore::execute_plan_with_resolver(&plan, &|_| {}, |_| {
+            asked += 1;
+            ConflictPolicy::Overwrite
+        })
+        .unwrap();
+        assert_eq!(asked, usize::from(policy == ConflictPolicy::Ask));
+        assert!(!result.has_errors(), "{:?}", result.errors);
+        assert_eq!(result.conflicts.len(), 1);
+        (dir, result)
+    }
+
+    #[test]
+    fn skip_leaves_both_files() {
+        let (dir, result) = run_with_conflict("conflict-skip", ConflictPolicy::Skip, "nuevo", "viejo", "src/a.txt");
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::Skipped);
+        assert_eq!(result.outcomes[0].status, OutcomeStatus::Skipped);
+        assert_eq!(text(&dir.join("src/a.txt")), "nuevo");
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "viejo");
+        assert!(result.run_id.is_none());
+    }
+
+    #[test]
+    fn overwrite_replaces_the_existing_file() {
+        for (test, policy) in [("conflict-overwrite", ConflictPolicy::Overwrite), ("conflict-ask", ConflictPolicy::Ask)] {
+            let (dir, result) = run_with_conflict(test, policy, "nuevo", "viejo", "src/a.txt");
+            assert_eq!(result.conflicts[0].resolution, ConflictResolution::Overwritten);
+            assert_eq!(result.conflicts[0].policy, ConflictPolicy::Overwrite);
+            assert!(!dir.join("src/a.txt").exists());
+            assert_eq!(text(&dir.join("out/TXT/a.txt")), "nuevo");
+        }
+    }
+
+    #[test]
+    fn undo_brings_back_the_overwritten_file() {
+        let cases = [
+            ("undo-overwrite", ConflictPolicy::Overwrite, "nuevo", "viejo", "src/a.txt"),
+            ("undo-newest", ConflictPolicy::KeepNewest, "nuevo", "viejo", "out/TXT/a.txt"),
+            ("undo-largest", ConflictPolicy::KeepLargest, "mas largo", "corto", "src/a.txt"),
+        ];
+        for (test, policy, source, existing, older) in cases {
+            let (dir, result) = run_with_conflict(test, policy, source, existing, older);
+            assert_eq!(result.conflicts[0].resolution, ConflictResolution::Overwritten);
+            assert_eq!(text(&dir.join("out/TXT/a.txt")), source);
+
+            let undo = FileOrganizerCore::undo_with_output(path_str(&dir.join("src")), Some(&dir.join("out")), None).unwrap();
+            assert!(undo.errors.is_none(), "{:?}", undo.errors);
+            assert_eq!((undo.restored, undo.recovered), (1, 1));
+            assert_eq!(text(&dir.join("src/a.txt")), source);
+            assert_eq!(text(&dir.join("out/TXT/a.txt")), existing);
+            // Del apartado no queda nada, ni el diario
+            assert!(!dir.join("out").join(JOURNAL_DIR).exists());
+        }
+    }
+
+    #[test]
+    fn undoing_an_overwriting_copy_restores_the_previous_file() {
+        let dir = TempDir::new("core-undo-copy-overwrite");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write(&src.join("a.txt"), "nuevo");
+        write(&out.join("TXT/a.txt"), "viejo");
+        let options = OrganizeOptions {
+            mode: FileOperationMode::Copy,
+            output_dir: Some(out.clone()),
+            conflict_policy: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+        FileOrganizerCore::organize_with_options(path_str(&src), &options, &|_| {}).unwrap();
+        assert_eq!(text(&out.join("TXT/a.txt")), "nuevo");
+
+        let undo = FileOrganizerCore::undo_with_output(path_str(&src), Some(&out), None).unwrap();
+        assert!(undo.errors.is_none(), "{:?}", undo.errors);
+        assert_eq!((undo.restored, undo.recovered), (1, 1));
+        assert_eq!(text(&src.join("a.txt")), "nuevo");
+        assert_eq!(text(&out.join("TXT/a.txt")), "viejo");
+
+        // Si la copia cambió después, no se borra y el anterior no se pone encima:
+        // las dos entradas quedan como errores pendientes en el diario
+        let result = FileOrganizerCore::organize_with_options(path_str(&src), &options, &|_| {}).unwrap();
+        write(&out.join("TXT/a.txt"), "editado despues");
+        let undo = FileOrganizerCore::undo_with_output(path_str(&src), Some(&out), result.run_id.as_deref()).unwrap();
+        assert_eq!((undo.restored, undo.recovered), (0, 0));
+        assert_eq!(undo.errors.as_ref().map(Vec::len), Some(2));
+        assert_eq!(text(&out.join("TXT/a.txt")), "editado despues");
+        assert_eq!(RunJournal::load(&out, result.run_id.as_deref().unwrap()).unwrap().entries.len(), 2);
+    }
+
+    #[test]
+    fn overwrite_within_a_run_can_be_undone() {
+        let dir = TempDir::new("core-overwrite-same-run");
+        let src = dir.join("src");
+        write(&src.join("uno/x.txt"), "uno");
+        write(&src.join("dos/x.txt"), "dos");
+        let options = OrganizeOptions {
+            scan: ScanOptions::recursive(),
+            conflict_policy: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+
+        let result = FileOrganizerCore::organize_with_options(path_str(&src), &options, &|_| {}).unwrap();
+        assert!(!result.has_errors(), "{:?}", result.errors);
+        assert_eq!(result.total_moved, 2);
+        // Queda el último en llegar; el primero está apartado, no perdido
+        let last = result.outcomes[1].source.parent().unwrap().file_name().unwrap();
+        assert_eq!(text(&src.join("Organizer/TXT/x.txt")), last.to_str().unwrap());
+
+        let undo = FileOrganizerCore::undo(path_str(&src), None).unwrap();
+        assert!(undo.errors.is_none(), "{:?}", undo.errors);
+        assert_eq!(text(&src.join("uno/x.txt")), "uno");
+        assert_eq!(text(&src.join("dos/x.txt")), "dos");
+        assert!(!src.join("Organizer").exists());
+    }
+
+    #[test]
+    fn rename_policies_keep_both_files() {
+        let (dir, result) = run_with_conflict("conflict-rename", ConflictPolicy::RenameWithSuffix, "nuevo", "viejo", "src/a.txt");
+        let renamed = dir.join("out/TXT/a (1).txt");
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::Renamed(renamed.clone()));
+        assert_eq!(result.outcomes[0].destination, renamed);
+        assert_eq!(text(&renamed), "nuevo");
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "viejo");
+
+        // El nombre elegido es el que queda en el diario: deshacer lo devuelve
+        let undo = FileOrganizerCore::undo_with_output(path_str(&dir.join("src")), Some(&dir.join("out")), None).unwrap();
+        assert_eq!(undo.restored, 1);
+        assert_eq!(text(&dir.join("src/a.txt")), "nuevo");
+        assert!(!renamed.exists());
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "viejo");
+
+        let (dir, result) = run_with_conflict("conflict-prefix", ConflictPolicy::PrefixSourceFolder, "nuevo", "viejo", "src/a.txt");
+        let renamed = dir.join("out/TXT/src_a.txt");
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::Renamed(renamed.clone()));
+        assert_eq!(text(&renamed), "nuevo");
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "viejo");
+    }
+
+    #[test]
+    fn keep_newest_compares_modification_dates() {
+        let (dir, result) = run_with_conflict("conflict-newest-old", ConflictPolicy::KeepNewest, "nuevo", "viejo", "src/a.txt");
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::KeptExisting);
+        assert_eq!(text(&dir.join("src/a.txt")), "nuevo");
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "viejo");
+
+        let (dir, result) = run_with_conflict("conflict-newest-new", ConflictPolicy::KeepNewest, "nuevo", "viejo", "out/TXT/a.txt");
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::Overwritten);
+        assert!(!dir.join("src/a.txt").exists());
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "nuevo");
+    }
+
+    #[test]
+    fn keep_largest_compares_sizes() {
+        let (dir, result) = run_with_conflict("conflict-largest-small", ConflictPolicy::KeepLargest, "corto", "mas largo", "out/TXT/a.txt");
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::KeptExisting);
+        assert_eq!(text(&dir.join("src/a.txt")), "corto");
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "mas largo");
+
+        let (dir, result) = run_with_conflict("conflict-largest-big", ConflictPolicy::KeepLargest, "mas largo", "corto", "src/a.txt");
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::Overwritten);
+        assert_eq!(text(&dir.join("out/TXT/a.txt")), "mas largo");
+    }
+
+    /// `src/dN/x.txt` van todos a `out/TXT/x.txt`, que ya existe; el resto de
+    /// archivos tienen destino propio
+    fn colliding_tree(test: &str) -> (TempDir, OrganizeOptions) {
+        let dir = TempDir::new(&format!("core-{}", test));
+        for i in 0..6 {
+            write(&dir.join(format!("src/d{}/x.txt", i)), &format!("d{}", i));
+        }
+        for i in 0..20 {
+            write(&dir.join(format!("src/f{}.txt", i)), &format!("f{}", i));
+        }
+        write(&dir.join("out/TXT/x.txt"), "previo");
+
+        let options = OrganizeOptions {
+            output_dir: Some(dir.join("out")),
+            scan: ScanOptions::recursive(),
+            jobs: 4,
+            ..Default::default()
+        };
+        (dir, options)
+    }
+
+    /// Cada archivo llegó a un nombre distinto sin pisar a ningún otro
+    fn check_no_collisions(dir: &Path, result: &OrganizerResult) {
+        assert!(!result.has_errors(), "{:?}", result.errors);
+        assert_eq!(result.total_moved, 26);
+        assert!(result.outcomes.iter().all(|outcome| outcome.status == OutcomeStatus::Done));
+
+        let mut contents: Vec<String> = fs::read_dir(dir.join("out/TXT"))
+            .unwrap()
+            .map(|entry| text(&entry.unwrap().path()))
+            .collect();
+        contents.sort();
+        let mut expected: Vec<String> = (0..6).map(|i| format!("d{}", i))
+            .chain((0..20).map(|i| format!("f{}", i)))
+            .chain(["previo".to_string()])
+            .collect();
+        expected.sort();
+        assert_eq!(contents, expected);
+
+        // Y cada uno tiene el contenido de su origen: `dN/x.txt` -> "dN", `fN.txt` -> "fN"
+        for outcome in &result.outcomes {
+            let stem = outcome.source.file_stem().unwrap();
+            let expected = if stem == "x" { outcome.source.parent().unwrap().file_name().unwrap() } else { stem };
+            assert_eq!(text(&outcome.destination), expected.to_str().unwrap());
+        }
+    }
+
+    #[test]
+    fn parallel_plan_resolves_colliding_destinations() {
+        let (dir, options) = colliding_tree("parallel-plan");
+        let plan = FileOrganizerCore::plan_organize(path_str(&dir.join("src")), &options).unwrap();
+        assert_eq!(plan.operations.iter().filter(|op| op.has_conflicts()).count(), 6);
+
+        let mut observed = Vec::new();
+        let result = FileOrganizerCore::execute_plan_with_observer(&plan, &|_| {}, |_| ConflictPolicy::Skip, |outcome| {
+            observed.push(outcome.source.clone())
+        })
+        .unwrap();
+        check_no_collisions(dir.path(), &result);
+        assert_eq!(result.conflicts.len(), 6);
+        // El resultado sigue el orden del plan aunque los hilos terminen en otro
+        let planned: Vec<_> = plan.operations.iter().map(|op| op.source.clone()).collect();
+        let finished: Vec<_> = result.outcomes.iter().map(|outcome| outcome.source.clone()).collect();
+        assert_eq!(finished, planned);
+        observed.sort();
+        let mut planned = planned;
+        planned.sort();
+        assert_eq!(observed, planned);
+
+        let undo = FileOrganizerCore::undo_with_output(path_str(&dir.join("src")), Some(&dir.join("out")), None).unwrap();
+        assert!(undo.errors.is_none(), "{:?}", undo.errors);
+        assert_eq!(undo.restored, 26);
+        assert_eq!(text(&dir.join("src/d3/x.txt")), "d3");
+        assert_eq!(text(&dir.join("out/TXT/x.txt")), "previo");
+    }
+
+    #[test]
+    fn parallel_streaming_resolves_colliding_destinations() {
+        let (dir, options) = colliding_tree("parallel-stream");
+        let result = FileOrganizerCore::organize_streaming(path_str(&dir.join("src")), &options, &|_| {}).unwrap();
+        check_no_collisions(dir.path(), &result);
+    }
+
+    #[test]
+    fn parallel_copy_keeps_the_sources() {
+        let (dir, mut options) = colliding_tree("parallel-copy");
+        options.mode = FileOperationMode::Copy;
+        options.verify_transfers = true;
+        let plan = FileOrganizerCore::plan_organize(path_str(&dir.join("src")), &options).unwrap();
+        let result = FileOrganizerCore::execute_plan(&plan, &|_| {}).unwrap();
+        check_no_collisions(dir.path(), &result);
+        assert_eq!(text(&dir.join("src/d5/x.txt")), "d5");
+        assert_eq!(text(&dir.join("src/f19.txt")), "f19");
+    }
+}
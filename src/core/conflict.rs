@@ -0,0 +1,177 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Qué hacer cuando el destino de un archivo ya existe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ConflictPolicy {
+    /// Dejar el archivo de origen donde está
+    Skip,
+    /// Reemplazar el archivo existente
+    Overwrite,
+    /// Añadir un sufijo numérico: `nombre (1).ext`
+    #[default]
+    RenameWithSuffix,
+    /// Anteponer la carpeta de origen: `carpeta_nombre.ext`
+    PrefixSourceFolder,
+    /// Conservar el más reciente de los dos
+    KeepNewest,
+    /// Conservar el más grande de los dos
+    KeepLargest,
+    /// Preguntar al usuario por cada conflicto
+    Ask,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 7] = [
+        ConflictPolicy::Skip,
+        ConflictPolicy::Overwrite,
+        ConflictPolicy::RenameWithSuffix,
+        ConflictPolicy::PrefixSourceFolder,
+        ConflictPolicy::KeepNewest,
+        ConflictPolicy::KeepLargest,
+        ConflictPolicy::Ask,
+    ];
+
+    /// Nombre usado en la CLI (`--on-conflict`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::RenameWithSuffix => "rename",
+            ConflictPolicy::PrefixSourceFolder => "prefix",
+            ConflictPolicy::KeepNewest => "newest",
+            ConflictPolicy::KeepLargest => "largest",
+            ConflictPolicy::Ask => "ask",
+        }
+    }
+
+    /// Texto mostrado en la interfaz gráfica
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Skip => "Omitir",
+            ConflictPolicy::Overwrite => "Sobrescribir",
+            ConflictPolicy::RenameWithSuffix => "Renombrar (sufijo)",
+            ConflictPolicy::PrefixSourceFolder => "Renombrar (carpeta de origen)",
+            ConflictPolicy::KeepNewest => "Conservar el mas reciente",
+            ConflictPolicy::KeepLargest => "Conservar el mas grande",
+            ConflictPolicy::Ask => "Preguntar",
+        }
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ConflictPolicy::ALL
+            .into_iter()
+            .find(|policy| policy.name() == s)
+            .ok_or_else(|| format!("Política de conflicto desconocida '{}'", s))
+    }
+}
+
+/// Cómo se resolvió un conflicto concreto
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConflictResolution {
+    /// El archivo de origen no se tocó
+    Skipped,
+    /// Se reemplazó el archivo que ya existía
+    Overwritten,
+    /// Se guardó con otro nombre
+    Renamed(PathBuf),
+    /// Se conservó el archivo existente y no se tocó el origen
+    KeptExisting,
+}
+
+impl fmt::Display for ConflictResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictResolution::Skipped => write!(f, "omitido"),
+            ConflictResolution::Overwritten => write!(f, "sobrescrito"),
+            ConflictResolution::Renamed(path) => write!(f, "renombrado a {}", path.display()),
+            ConflictResolution::KeptExisting => write!(f, "se conservo el existente"),
+        }
+    }
+}
+
+/// Conflicto encontrado al ejecutar un plan y cómo se resolvió
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictRecord {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub policy: ConflictPolicy,
+    pub resolution: ConflictResolution,
+}
+
+/// Decide el destino final de `source` cuando `destination` ya existe.
+/// Devuelve `None` si el archivo no debe moverse ni copiarse.
+pub(crate) fn resolve(
+    source: &Path,
+    destination: &Path,
+    policy: ConflictPolicy,
+) -> (Option<PathBuf>, ConflictResolution) {
+    match policy {
+        ConflictPolicy::Skip | ConflictPolicy::Ask => (None, ConflictResolution::Skipped),
+        ConflictPolicy::Overwrite => (Some(destination.to_path_buf()), ConflictResolution::Overwritten),
+        ConflictPolicy::RenameWithSuffix => {
+            let renamed = unique_path(destination);
+            (Some(renamed.clone()), ConflictResolution::Renamed(renamed))
+        }
+        ConflictPolicy::PrefixSourceFolder => {
+            let prefix = source.parent()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "raiz".to_string());
+            let file_name = destination.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let renamed = unique_path(&destination.with_file_name(format!("{}_{}", prefix, file_name)));
+            (Some(renamed.clone()), ConflictResolution::Renamed(renamed))
+        }
+        ConflictPolicy::KeepNewest => {
+            let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+            if modified(source) > modified(destination) {
+                (Some(destination.to_path_buf()), ConflictResolution::Overwritten)
+            } else {
+                (None, ConflictResolution::KeptExisting)
+            }
+        }
+        ConflictPolicy::KeepLargest => {
+            let size = |p: &Path| fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+            if size(source) > size(destination) {
+                (Some(destination.to_path_buf()), ConflictResolution::Overwritten)
+            } else {
+                (None, ConflictResolution::KeptExisting)
+            }
+        }
+    }
+}
+
+/// Busca un nombre libre añadiendo ` (N)` antes de la extensión
+pub(crate) fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path.extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .expect("siempre hay un nombre libre")
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// Plantilla por defecto: `Organizer/2025/03/`
+pub const DEFAULT_DATE_TEMPLATE: &str = "{year}/{month}";
+
+/// Fecha usada para agrupar cada archivo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DateSource {
+    /// Última modificación
+    #[default]
+    Modified,
+    /// Creación (si el sistema de archivos no la guarda, se usa la modificación)
+    Created,
+    /// `DateTimeOriginal` de EXIF (si la foto no la tiene, se usa la modificación)
+    Taken,
+}
+
+impl DateSource {
+    pub const ALL: [DateSource; 3] = [DateSource::Modified, DateSource::Created, DateSource::Taken];
+
+    /// Nombre usado en la CLI (`--date-source`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            DateSource::Modified => "modified",
+            DateSource::Created => "created",
+            DateSource::Taken => "taken",
+        }
+    }
+
+    /// Texto mostrado en la interfaz gráfica
+    pub fn label(&self) -> &'static str {
+        match self {
+            DateSource::Modified => "Fecha de modificacion",
+            DateSource::Created => "Fecha de creacion",
+            DateSource::Taken => "Fecha de captura (EXIF)",
+        }
+    }
+}
+
+impl fmt::Display for DateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for DateSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DateSource::ALL
+            .into_iter()
+            .find(|source| source.name() == s)
+            .ok_or_else(|| format!("Origen de fecha desconocido '{}'", s))
+    }
+}
+
+/// Agrupación por fecha: de dónde sale la fecha y cómo se convierte en carpetas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DateGrouping {
+    pub source: DateSource,
+    /// Ruta relativa con `{year}`, `{month}` y `{day}` (p. ej. `{year}/{month}`)
+    pub template: String,
+}
+
+impl Default for DateGrouping {
+    fn default() -> Self {
+        DateGrouping {
+            source: DateSource::default(),
+            template: DEFAULT_DATE_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl DateGrouping {
+    const PLACEHOLDERS: [&'static str; 3] = ["year", "month", "day"];
+
+    /// Comprueba que la plantilla solo use marcadores conocidos y no salga de `Organizer/`
+    pub fn validate(&self) -> Result<(), String> {
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Falta '}}' en la plantilla '{}'", self.template))?;
+            let name = &rest[start + 1..start + end];
+            if !Self::PLACEHOLDERS.contains(&name) {
+                return Err(format!("Marcador desconocido '{{{}}}' en la plantilla", name));
+            }
+            rest = &rest[start + end + 1..];
+        }
+
+        if self.template.trim().is_empty()
+            || self.template.starts_with(['/', '\\'])
+            || self.template.split(['/', '\\']).any(|part| part == "..")
+        {
+            return Err(format!("Plantilla de fecha no válida: '{}'", self.template));
+        }
+        Ok(())
+    }
+
+    /// Carpeta relativa de un archivo y la fecha usada, o `None` si no se pudo leer ninguna
+    pub fn folder_for(&self, path: &Path) -> Option<(String, DateTime<Local>)> {
+        let date = file_date(path, self.source)?;
+        let folder = self.template
+            .replace("{year}", &format!("{:04}", date.year()))
+            .replace("{month}", &format!("{:02}", date.month()))
+            .replace("{day}", &format!("{:02}", date.day()));
+        Some((folder, date))
+    }
+}
+
+/// Fecha de un archivo según el origen pedido
+pub fn file_date(path: &Path, source: DateSource) -> Option<DateTime<Local>> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok().map(DateTime::<Local>::from);
+
+    match source {
+        DateSource::Modified => modified,
+        DateSource::Created => metadata.created().ok().map(DateTime::<Local>::from).or(modified),
+        DateSource::Taken => exif_date_taken(path).or(modified),
+    }
+}
+
+/// Lee `DateTimeOriginal` (o `DateTime`) de los metadatos EXIF
+pub fn exif_date_taken(path: &Path) -> Option<DateTime<Local>> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    let field = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .or_else(|| exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY))?;
+
+    let exif::Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(values.first()?);
+
+    // EXIF guarda la hora local de la cámara sin zona horaria
+    NaiveDateTime::parse_from_str(text.trim(), "%Y:%m:%d %H:%M:%S")
+        .ok()?
+        .and_local_timezone(Local)
+        .earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::core::testing::TempDir;
+
+    /// Archivo con fecha de modificación fija
+    fn file_modified(dir: &TempDir, name: &str, contents: &[u8], modified: DateTime<Local>) -> std::path::PathBuf {
+        let path = dir.write(name, contents);
+        File::options().write(true).open(&path).unwrap().set_modified(SystemTime::from(modified)).unwrap();
+        path
+    }
+
+    fn local(year: i32, month: u32, day: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap()
+    }
+
+    /// JPEG mínimo con solo la etiqueta EXIF `DateTime` en la primera IFD
+    fn jpeg_with_exif_date(date: &str) -> Vec<u8> {
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        tiff.extend([1, 0]);
+        tiff.extend([0x32, 0x01, 2, 0]);
+        tiff.extend(20u32.to_le_bytes());
+        tiff.extend(26u32.to_le_bytes());
+        tiff.extend([0; 4]);
+        tiff.extend(date.as_bytes());
+        tiff.push(0);
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend((app1.len() as u16 + 2).to_be_bytes());
+        jpeg.extend(app1);
+        jpeg.extend([0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn template_builds_year_month_day_folders() {
+        let dir = TempDir::new("dates-template");
+        let path = file_modified(&dir, "a.txt", b"x", local(2024, 3, 7));
+
+        let (folder, date) = DateGrouping::default().folder_for(&path).unwrap();
+        assert_eq!(folder, "2024/03");
+        assert_eq!(date.day(), 7);
+
+        let grouping = DateGrouping { template: "{year}/{month}/{day}".to_string(), ..Default::default() };
+        assert_eq!(grouping.folder_for(&path).unwrap().0, "2024/03/07");
+        assert!(grouping.folder_for(&dir.join("missing.txt")).is_none());
+    }
+
+    #[test]
+    fn validate_rejects_unknown_markers_and_escaping_paths() {
+        for template in ["{year}/{week}", "{year", "", "/{year}", "../{year}", "{year}/../x"] {
+            let grouping = DateGrouping { template: template.to_string(), ..Default::default() };
+            assert!(grouping.validate().is_err(), "{:?}", template);
+        }
+        let grouping = DateGrouping { template: "Fotos/{year}-{month}-{day}".to_string(), ..Default::default() };
+        assert!(grouping.validate().is_ok());
+    }
+
+    #[test]
+    fn taken_date_comes_from_exif_or_falls_back_to_modified() {
+        let dir = TempDir::new("dates-exif");
+        let modified = local(2024, 3, 7);
+
+        let with_exif = file_modified(&dir, "a.jpg", &jpeg_with_exif_date("2021:07:04 10:20:30"), modified);
+        let taken = file_date(&with_exif, DateSource::Taken).unwrap();
+        assert_eq!(taken, Local.with_ymd_and_hms(2021, 7, 4, 10, 20, 30).unwrap());
+        assert_eq!(file_date(&with_exif, DateSource::Modified), Some(modified));
+
+        let without_exif = file_modified(&dir, "b.jpg", b"no es una foto", modified);
+        assert!(exif_date_taken(&without_exif).is_none());
+        assert_eq!(file_date(&without_exif, DateSource::Taken), Some(modified));
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::{Operation, OrganizerError};
+use super::progress::Progress;
+use super::ProgressCallback;
+use super::transfer::hash_file;
+
+/// Bytes del principio de cada archivo usados para el hash rápido
+const PARTIAL_HASH_SIZE: u64 = 64 * 1024;
+
+/// Carpeta (dentro de la de destino) donde se apartan las copias sobrantes
+pub const QUARANTINE_DIR: &str = "Duplicates";
+
+/// Qué copia de cada grupo de duplicados se conserva
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum KeepRule {
+    /// La modificada hace más tiempo
+    Oldest,
+    /// La modificada más recientemente
+    Newest,
+    /// La de ruta más corta
+    #[default]
+    ShortestPath,
+    /// La que está dentro de esta carpeta; si hay varias, o ninguna, la de ruta más corta
+    InFolder(PathBuf),
+}
+
+impl KeepRule {
+    /// Nombre usado en la CLI (`--keep`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeepRule::Oldest => "oldest",
+            KeepRule::Newest => "newest",
+            KeepRule::ShortestPath => "shortest",
+            KeepRule::InFolder(_) => "folder",
+        }
+    }
+
+    /// Texto mostrado en la interfaz gráfica
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeepRule::Oldest => "La mas antigua",
+            KeepRule::Newest => "La mas reciente",
+            KeepRule::ShortestPath => "La de ruta mas corta",
+            KeepRule::InFolder(_) => "La de una carpeta",
+        }
+    }
+
+    /// La misma regla con la carpeta preferida, si es relativa, dentro de `root`
+    pub fn within(&self, root: &Path) -> KeepRule {
+        match self {
+            KeepRule::InFolder(folder) if folder.is_relative() => KeepRule::InFolder(root.join(folder)),
+            rule => rule.clone(),
+        }
+    }
+}
+
+impl fmt::Display for KeepRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeepRule::InFolder(folder) => write!(f, "folder {}", folder.display()),
+            rule => write!(f, "{}", rule.name()),
+        }
+    }
+}
+
+impl FromStr for KeepRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest" => Ok(KeepRule::Oldest),
+            "newest" => Ok(KeepRule::Newest),
+            "shortest" => Ok(KeepRule::ShortestPath),
+            _ => Err(format!("Regla desconocida '{}' (oldest, newest, shortest)", s)),
+        }
+    }
+}
+
+/// Qué se hace con las copias que no se conservan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DuplicateAction {
+    /// Borrarlas
+    Delete,
+    /// Sustituirlas por enlaces duros a la copia conservada
+    HardLink,
+    /// Sustituirlas por enlaces simbólicos a la copia conservada
+    SymLink,
+    /// Moverlas a `Duplicates/` dentro de la carpeta de destino
+    #[default]
+    Quarantine,
+}
+
+impl DuplicateAction {
+    pub const ALL: [DuplicateAction; 4] = [
+        DuplicateAction::Quarantine,
+        DuplicateAction::Delete,
+        DuplicateAction::HardLink,
+        DuplicateAction::SymLink,
+    ];
+
+    /// Nombre usado en la CLI (`--action`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            DuplicateAction::Delete => "delete",
+            DuplicateAction::HardLink => "hardlink",
+            DuplicateAction::SymLink => "symlink",
+            DuplicateAction::Quarantine => "quarantine",
+        }
+    }
+
+    /// Texto mostrado en la interfaz gráfica
+    pub fn label(&self) -> &'static str {
+        match self {
+            DuplicateAction::Delete => "Borrar",
+            DuplicateAction::HardLink => "Sustituir por enlaces duros",
+            DuplicateAction::SymLink => "Sustituir por enlaces simbolicos",
+            DuplicateAction::Quarantine => "Mover a Duplicates/",
+        }
+    }
+
+    /// Indica si la acción libera el espacio de las copias sobrantes
+    pub fn frees_space(&self) -> bool {
+        *self != DuplicateAction::Quarantine
+    }
+}
+
+impl fmt::Display for DuplicateAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for DuplicateAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DuplicateAction::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("Acción desconocida '{}' (delete, hardlink, symlink, quarantine)", s))
+    }
+}
+
+/// Archivos con el mismo contenido
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    /// Tamaño de cada copia en bytes
+    pub size: u64,
+    /// Hash BLAKE3 del contenido completo
+    pub hash: String,
+    /// Rutas de todas las copias, ordenadas
+    pub files: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes que se liberarían dejando una sola copia
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.size * (self.files.len().saturating_sub(1)) as u64
+    }
+
+    /// Copia que se conserva según la regla; las demás son sobrantes
+    pub fn keeper(&self, rule: &KeepRule) -> &Path {
+        let modified = |path: &PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        let kept = match rule {
+            // A igual fecha gana el primero en orden, que es el de la lista ordenada
+            KeepRule::Oldest => self.files.iter().min_by_key(|path| modified(path)),
+            KeepRule::Newest => self.files.iter().rev().max_by_key(|path| modified(path)),
+            KeepRule::ShortestPath => shortest(self.files.iter()),
+            KeepRule::InFolder(folder) => shortest(self.files.iter().filter(|path| path.starts_with(folder)))
+                .or_else(|| shortest(self.files.iter())),
+        };
+        kept.expect("un grupo siempre tiene archivos")
+    }
+
+    /// Copias que sobran si se conserva la que elige la regla
+    pub fn extras(&self, rule: &KeepRule) -> impl Iterator<Item = &Path> {
+        let kept = self.keeper(rule);
+        self.files.iter().map(PathBuf::as_path).filter(move |path| *path != kept)
+    }
+}
+
+/// Resultado de buscar duplicados en una carpeta
+#[derive(Debug, Serialize)]
+pub struct DuplicateReport {
+    pub root: PathBuf,
+    /// Grupos de duplicados, primero los que más espacio ocupan
+    pub groups: Vec<DuplicateGroup>,
+    pub files_scanned: usize,
+    pub reclaimable_bytes: u64,
+    /// Archivos que no se pudieron leer
+    pub errors: Vec<OrganizerError>,
+}
+
+impl DuplicateReport {
+    pub fn duplicate_files(&self) -> usize {
+        self.groups.iter().map(|group| group.files.len() - 1).sum()
+    }
+}
+
+/// Agrupa archivos por contenido: primero por tamaño, luego por un hash del
+/// principio del archivo y por último por el hash completo. El avance cuenta
+/// los archivos que comparten tamaño con algún otro, que son los que se leen.
+pub(crate) fn find_duplicates(root: &Path, files: Vec<PathBuf>, progress_callback: &ProgressCallback<'_>) -> DuplicateReport {
+    let files_scanned = files.len();
+    let mut errors = Vec::new();
+
+    // Tamaño: solo pueden ser iguales los archivos del mismo tamaño
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut seen_inodes = HashSet::new();
+    for path in files {
+        match fs::metadata(&path) {
+            // Los archivos vacíos no ocupan nada que recuperar
+            Ok(metadata) if metadata.len() == 0 => {}
+            Ok(metadata) => {
+                // Dos enlaces duros al mismo archivo no son dos copias
+                if let Some(inode) = inode_key(&metadata)
+                    && !seen_inodes.insert(inode)
+                {
+                    continue;
+                }
+                by_size.entry(metadata.len()).or_default().push(path);
+            }
+            Err(e) => errors.push(OrganizerError::io(Operation::ReadMetadata, &path, e)),
+        }
+    }
+
+    let by_size: Vec<(u64, Vec<PathBuf>)> = by_size.into_iter().filter(|(_, files)| files.len() > 1).collect();
+    let total = by_size.iter().map(|(_, files)| files.len()).sum();
+    let bytes_total = by_size.iter().map(|(size, files)| size * files.len() as u64).sum();
+    let started = Instant::now();
+    let mut current = 0;
+    let mut bytes_done = 0;
+
+    let mut groups = Vec::new();
+    for (size, candidates) in by_size {
+        // Hash rápido del principio; en archivos pequeños ya es el hash completo
+        let mut by_partial: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+        for path in candidates {
+            progress_callback(&Progress {
+                current: current + 1,
+                total,
+                file: path.clone(),
+                bytes_done,
+                bytes_total,
+                elapsed: started.elapsed(),
+                estimated: false,
+            });
+            current += 1;
+            bytes_done += size;
+            match partial_hash(&path) {
+                Ok(hash) => by_partial.entry(hash).or_default().push(path),
+                Err(e) => errors.push(OrganizerError::io(Operation::Hash, &path, e)),
+            }
+        }
+
+        for (partial, candidates) in by_partial.into_iter().filter(|(_, files)| files.len() > 1) {
+            if size <= PARTIAL_HASH_SIZE {
+                groups.push(DuplicateGroup { size, hash: partial.to_hex().to_string(), files: candidates });
+                continue;
+            }
+
+            // Confirmación con el contenido completo
+            let mut by_full: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+            for path in candidates {
+                match hash_file(&path) {
+                    Ok(hash) => by_full.entry(hash).or_default().push(path),
+                    Err(e) => errors.push(OrganizerError::io(Operation::Hash, &path, e)),
+                }
+            }
+            groups.extend(
+                by_full
+                    .into_iter()
+                    .filter(|(_, files)| files.len() > 1)
+                    .map(|(hash, files)| DuplicateGroup { size, hash: hash.to_hex().to_string(), files }),
+            );
+        }
+    }
+
+    for group in &mut groups {
+        group.files.sort();
+    }
+    groups.sort_by(|a, b| {
+        b.reclaimable_bytes()
+            .cmp(&a.reclaimable_bytes())
+            .then_with(|| a.files.cmp(&b.files))
+    });
+
+    DuplicateReport {
+        root: root.to_path_buf(),
+        reclaimable_bytes: groups.iter().map(DuplicateGroup::reclaimable_bytes).sum(),
+        groups,
+        files_scanned,
+        errors,
+    }
+}
+
+/// Ruta más corta; a igual longitud, la primera en orden alfabético
+fn shortest<'a>(files: impl Iterator<Item = &'a PathBuf>) -> Option<&'a PathBuf> {
+    files.min_by(|a, b| a.as_os_str().len().cmp(&b.as_os_str().len()).then_with(|| a.cmp(b)))
+}
+
+/// Hash BLAKE3 de los primeros `PARTIAL_HASH_SIZE` bytes
+fn partial_hash(path: &Path) -> io::Result<blake3::Hash> {
+    let mut buffer = Vec::with_capacity(PARTIAL_HASH_SIZE as usize);
+    File::open(path)?.take(PARTIAL_HASH_SIZE).read_to_end(&mut buffer)?;
+    Ok(blake3::hash(&buffer))
+}
+
+#[cfg(unix)]
+fn inode_key(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode_key(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
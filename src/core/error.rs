@@ -0,0 +1,184 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// Operación sobre el disco durante la que ocurrió un error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    ReadDir,
+    ReadMetadata,
+    ReadFile,
+    WriteFile,
+    ResolvePath,
+    CreateDir,
+    Move,
+    Copy,
+    Delete,
+    Link,
+    Hash,
+    Restore,
+    ReadJournal,
+    WriteJournal,
+    DeleteJournal,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Operation::ReadDir => "leer el directorio",
+            Operation::ReadMetadata => "leer los metadatos de",
+            Operation::ReadFile => "leer",
+            Operation::WriteFile => "escribir",
+            Operation::ResolvePath => "resolver la ruta",
+            Operation::CreateDir => "crear la carpeta",
+            Operation::Move => "mover",
+            Operation::Copy => "copiar",
+            Operation::Delete => "borrar",
+            Operation::Link => "enlazar",
+            Operation::Hash => "calcular el hash de",
+            Operation::Restore => "restaurar",
+            Operation::ReadJournal => "leer el diario",
+            Operation::WriteJournal => "escribir el diario",
+            Operation::DeleteJournal => "borrar el diario",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Error de una operación del organizador, tanto los que detienen una
+/// ejecución como los de un archivo concreto
+#[derive(Debug)]
+pub enum OrganizerError {
+    /// La ruta no existe
+    NotFound { path: PathBuf },
+    /// La ruta existe pero no es una carpeta
+    NotADirectory { path: PathBuf },
+    /// La carpeta de destino es la misma que la de origen
+    SameSourceAndDestination { path: PathBuf },
+    /// Fallo de entrada/salida
+    Io {
+        operation: Operation,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Ya hay un archivo donde la operación tenía que escribir
+    Collision { operation: Operation, path: PathBuf },
+    /// El archivo cambió desde que se planificó o registró la operación
+    Changed { operation: Operation, path: PathBuf },
+    /// Opciones, reglas, plantilla o patrones no válidos
+    InvalidConfig(String),
+    /// No hay diario para esa ejecución
+    RunNotFound { run_id: String },
+    /// No queda ninguna ejecución que deshacer
+    NothingToUndo,
+    /// El diario existe pero no se puede interpretar
+    CorruptJournal { path: PathBuf, message: String },
+}
+
+impl OrganizerError {
+    pub(crate) fn io(operation: Operation, path: &Path, source: io::Error) -> Self {
+        OrganizerError::Io { operation, path: path.to_path_buf(), source }
+    }
+
+    /// Nombre estable del tipo de error, pensado para herramientas
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OrganizerError::NotFound { .. } => "not_found",
+            OrganizerError::NotADirectory { .. } => "not_a_directory",
+            OrganizerError::SameSourceAndDestination { .. } => "same_source_and_destination",
+            OrganizerError::Io { source, .. } if source.kind() == io::ErrorKind::PermissionDenied => "permission_denied",
+            OrganizerError::Io { .. } => "io",
+            OrganizerError::Collision { .. } => "collision",
+            OrganizerError::Changed { .. } => "changed",
+            OrganizerError::InvalidConfig(_) => "invalid_config",
+            OrganizerError::RunNotFound { .. } => "run_not_found",
+            OrganizerError::NothingToUndo => "nothing_to_undo",
+            OrganizerError::CorruptJournal { .. } => "corrupt_journal",
+        }
+    }
+
+    /// Ruta afectada, si el error tiene una
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            OrganizerError::NotFound { path }
+            | OrganizerError::NotADirectory { path }
+            | OrganizerError::SameSourceAndDestination { path }
+            | OrganizerError::Io { path, .. }
+            | OrganizerError::Collision { path, .. }
+            | OrganizerError::Changed { path, .. }
+            | OrganizerError::CorruptJournal { path, .. } => Some(path),
+            OrganizerError::InvalidConfig(_) | OrganizerError::RunNotFound { .. } | OrganizerError::NothingToUndo => None,
+        }
+    }
+
+    /// Operación que falló, si fue una operación sobre el disco
+    pub fn operation(&self) -> Option<Operation> {
+        match self {
+            OrganizerError::Io { operation, .. }
+            | OrganizerError::Collision { operation, .. }
+            | OrganizerError::Changed { operation, .. } => Some(*operation),
+            _ => None,
+        }
+    }
+
+    /// Tipo del error de E/S subyacente (p. ej. `PermissionDenied`)
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            OrganizerError::Io { source, .. } => Some(source.kind()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for OrganizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrganizerError::NotFound { path } => write!(f, "La ruta '{}' no existe", path.display()),
+            OrganizerError::NotADirectory { path } => write!(f, "'{}' no es un directorio", path.display()),
+            OrganizerError::SameSourceAndDestination { .. } => {
+                write!(f, "La carpeta de destino no puede ser la misma que la de origen")
+            }
+            OrganizerError::Io { operation, path, source } => {
+                write!(f, "Error al {} '{}': {}", operation, path.display(), source)
+            }
+            OrganizerError::Collision { operation, path } => {
+                write!(f, "No se puede {}: ya existe '{}'", operation, path.display())
+            }
+            OrganizerError::Changed { operation, path } => {
+                write!(f, "'{}' ha cambiado; no se puede {}", path.display(), operation)
+            }
+            OrganizerError::InvalidConfig(message) => write!(f, "{}", message),
+            OrganizerError::RunNotFound { run_id } => write!(f, "No existe la ejecución '{}'", run_id),
+            OrganizerError::NothingToUndo => write!(f, "No hay ejecuciones para deshacer"),
+            OrganizerError::CorruptJournal { path, message } => {
+                write!(f, "Diario '{}' corrupto: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl Error for OrganizerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OrganizerError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Se serializa con el tipo, la operación, la ruta y el mensaje legible
+impl Serialize for OrganizerError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("OrganizerError", 4)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("operation", &self.operation())?;
+        state.serialize_field("path", &self.path())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
@@ -0,0 +1,309 @@
+use std::fmt;
+use std::fs::Metadata;
+use std::str::FromStr;
+
+use chrono::{DateTime, Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use super::rules::wildcard_match;
+
+/// Límite de fecha: un día concreto o hace un número de días
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateBound {
+    DaysAgo(u64),
+    Date(NaiveDate),
+}
+
+impl DateBound {
+    /// Instante equivalente, a medianoche en el caso de una fecha
+    pub fn instant(&self) -> Option<DateTime<Local>> {
+        match self {
+            DateBound::DaysAgo(days) => Local::now().checked_sub_days(Days::new(*days)),
+            DateBound::Date(date) => date.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest(),
+        }
+    }
+}
+
+impl fmt::Display for DateBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateBound::DaysAgo(days) => write!(f, "{}d", days),
+            DateBound::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+        }
+    }
+}
+
+impl FromStr for DateBound {
+    type Err = String;
+
+    /// `7d` (hace 7 días) o `2025-01-31`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(days) = s.strip_suffix('d') {
+            return days
+                .parse()
+                .map(DateBound::DaysAgo)
+                .map_err(|_| format!("Número de días no válido '{}'", s));
+        }
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(DateBound::Date)
+            .map_err(|_| format!("Fecha no válida '{}' (se esperaba AAAA-MM-DD o un número de días como 7d)", s))
+    }
+}
+
+/// Criterios para elegir qué archivos se organizan, además de las rutas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileFilter {
+    /// Tamaño mínimo en bytes
+    pub min_size: Option<u64>,
+    /// Tamaño máximo en bytes
+    pub max_size: Option<u64>,
+    /// Solo archivos modificados antes de este límite (p. ej. hace más de 7 días)
+    pub modified_before: Option<DateBound>,
+    /// Solo archivos modificados a partir de este límite
+    pub modified_after: Option<DateBound>,
+    /// Patrones sobre el nombre con `*` y `?`; basta con que coincida uno
+    pub name_patterns: Vec<String>,
+    /// Incluir archivos y carpetas ocultos
+    pub include_hidden: bool,
+    /// Basta con que se cumpla un criterio en lugar de todos
+    pub match_any: bool,
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        FileFilter {
+            min_size: None,
+            max_size: None,
+            modified_before: None,
+            modified_after: None,
+            name_patterns: Vec::new(),
+            include_hidden: true,
+            match_any: false,
+        }
+    }
+}
+
+impl FileFilter {
+    /// Indica si hay algún criterio además de los archivos ocultos
+    pub fn has_criteria(&self) -> bool {
+        self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.modified_before.is_some()
+            || self.modified_after.is_some()
+            || !self.name_patterns.is_empty()
+    }
+
+    /// Comprueba que los criterios no se contradigan
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(min), Some(max)) = (self.min_size, self.max_size)
+            && min > max
+            && !self.match_any
+        {
+            return Err(format!("El tamaño mínimo ({}) es mayor que el máximo ({})", min, max));
+        }
+        for bound in self.modified_before.iter().chain(&self.modified_after) {
+            bound.instant().ok_or_else(|| format!("Fecha fuera de rango: {}", bound))?;
+        }
+        Ok(())
+    }
+
+    /// Indica si una entrada (archivo o carpeta) se salta por estar oculta
+    pub fn hides(&self, name: &str, metadata: &Metadata) -> bool {
+        !self.include_hidden && is_hidden(name, metadata)
+    }
+
+    /// Indica si un archivo cumple los criterios
+    pub fn matches(&self, name: &str, metadata: &Metadata) -> bool {
+        if self.hides(name, metadata) {
+            return false;
+        }
+        if !self.has_criteria() {
+            return true;
+        }
+
+        let size = metadata.len();
+        let modified = metadata.modified().ok().map(DateTime::<Local>::from);
+        let name = name.to_lowercase();
+
+        let checks = [
+            self.min_size.map(|min| size >= min),
+            self.max_size.map(|max| size <= max),
+            self.modified_before
+                .and_then(|bound| bound.instant())
+                .map(|limit| modified.is_some_and(|m| m < limit)),
+            self.modified_after
+                .and_then(|bound| bound.instant())
+                .map(|limit| modified.is_some_and(|m| m >= limit)),
+            (!self.name_patterns.is_empty()).then(|| {
+                self.name_patterns
+                    .iter()
+                    .any(|pattern| wildcard_match(&pattern.to_lowercase(), &name))
+            }),
+        ];
+        let mut active = checks.into_iter().flatten();
+
+        if self.match_any {
+            active.any(|passed| passed)
+        } else {
+            active.all(|passed| passed)
+        }
+    }
+}
+
+/// Nombre que empieza por punto o, en Windows, con el atributo de oculto
+#[cfg(windows)]
+fn is_hidden(name: &str, metadata: &Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    name.starts_with('.') || metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+/// Nombre que empieza por punto
+#[cfg(not(windows))]
+fn is_hidden(name: &str, _metadata: &Metadata) -> bool {
+    name.starts_with('.')
+}
+
+/// Unidades que acepta `parse_size`, sin distinguir mayúsculas (múltiplos de 1024)
+const SIZE_UNITS: [(&str, u64); 14] = [
+    ("", 1),
+    ("B", 1),
+    ("K", 1 << 10),
+    ("KB", 1 << 10),
+    ("KIB", 1 << 10),
+    ("M", 1 << 20),
+    ("MB", 1 << 20),
+    ("MIB", 1 << 20),
+    ("G", 1 << 30),
+    ("GB", 1 << 30),
+    ("GIB", 1 << 30),
+    ("T", 1 << 40),
+    ("TB", 1 << 40),
+    ("TIB", 1 << 40),
+];
+
+/// Interpreta un tamaño como `500`, `64K`, `10M`, `1.5G`, `2GB` o `3MiB` (múltiplos de 1024)
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let invalid = || format!("Tamaño no válido '{}'", text);
+    let unit_start = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(unit_start);
+    let unit = unit.trim().to_uppercase();
+    let multiplier = SIZE_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, multiplier)| *multiplier)
+        .ok_or_else(invalid)?;
+
+    let value: f64 = number.parse().map_err(|_| invalid())?;
+    if !value.is_finite() {
+        return Err(invalid());
+    }
+    Ok((value * multiplier as f64) as u64)
+}
+
+/// Tamaño legible con la misma unidad que acepta `parse_size`: `512 B`, `1.5 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::core::testing::TempDir;
+
+    #[test]
+    fn sizes_with_and_without_units() {
+        assert_eq!(parse_size("500"), Ok(500));
+        assert_eq!(parse_size(" 12 B "), Ok(12));
+        assert_eq!(parse_size("64K"), Ok(64 * 1024));
+        assert_eq!(parse_size("64kb"), Ok(64 * 1024));
+        assert_eq!(parse_size("3MiB"), Ok(3 << 20));
+        assert_eq!(parse_size("1.5G"), Ok(3 << 29));
+        assert_eq!(parse_size("2 GB"), Ok(2 << 30));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+    }
+
+    #[test]
+    fn malformed_sizes_are_rejected() {
+        for text in ["", "abc", "10BIB", "10KK", "10 KBB", "10I", "5X", "-1", "1.2.3", "M"] {
+            assert!(parse_size(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn date_bounds() {
+        assert_eq!("7d".parse(), Ok(DateBound::DaysAgo(7)));
+        assert_eq!(" 2025-01-31 ".parse(), Ok(DateBound::Date(NaiveDate::from_ymd_opt(2025, 1, 31).unwrap())));
+        assert!("xd".parse::<DateBound>().is_err());
+        assert!("2025-13-01".parse::<DateBound>().is_err());
+        assert_eq!(DateBound::DaysAgo(3).to_string(), "3d");
+    }
+
+    /// Metadatos de un archivo de `size` bytes
+    fn file(dir: &TempDir, name: &str, size: usize) -> Metadata {
+        fs::metadata(dir.write(name, vec![b'x'; size])).unwrap()
+    }
+
+    #[test]
+    fn size_limits_include_their_edges() {
+        let dir = TempDir::new("filter-size");
+        let filter = FileFilter { min_size: Some(10), max_size: Some(20), ..Default::default() };
+        assert!(!filter.matches("a", &file(&dir, "a", 9)));
+        assert!(filter.matches("b", &file(&dir, "b", 10)));
+        assert!(filter.matches("c", &file(&dir, "c", 20)));
+        assert!(!filter.matches("d", &file(&dir, "d", 21)));
+
+        let inverted = FileFilter { min_size: Some(20), max_size: Some(10), ..Default::default() };
+        assert!(inverted.validate().is_err());
+        assert!(FileFilter { match_any: true, ..inverted }.validate().is_ok());
+    }
+
+    #[test]
+    fn match_any_needs_one_criterion_instead_of_all() {
+        let dir = TempDir::new("filter-any");
+        let small_log = file(&dir, "small.log", 1);
+        let big_txt = file(&dir, "big.txt", 100);
+        let big_log = file(&dir, "big.log", 100);
+        let all = FileFilter { min_size: Some(50), name_patterns: vec!["*.LOG".to_string()], ..Default::default() };
+        let any = FileFilter { match_any: true, ..all.clone() };
+
+        assert!(!all.matches("small.log", &small_log));
+        assert!(!all.matches("big.txt", &big_txt));
+        assert!(all.matches("big.log", &big_log));
+
+        assert!(any.matches("small.log", &small_log));
+        assert!(any.matches("big.txt", &big_txt));
+        assert!(!any.matches("tiny.txt", &file(&dir, "tiny.txt", 1)));
+    }
+
+    #[test]
+    fn hidden_files_follow_include_hidden() {
+        let dir = TempDir::new("filter-hidden");
+        let hidden = file(&dir, ".secret", 1);
+        assert!(FileFilter::default().matches(".secret", &hidden));
+
+        let filter = FileFilter { include_hidden: false, ..Default::default() };
+        assert!(filter.hides(".secret", &hidden));
+        assert!(!filter.matches(".secret", &hidden));
+        assert!(filter.matches("visible", &file(&dir, "visible", 1)));
+        assert!(!filter.has_criteria());
+    }
+}
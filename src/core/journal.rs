@@ -0,0 +1,413 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::error::{Operation, OrganizerError};
+use super::FileOperationMode;
+
+/// Carpeta (dentro de `Organizer/`) donde se guardan los diarios de cada ejecución
+pub const JOURNAL_DIR: &str = ".journal";
+
+/// Qué se hizo con el archivo de una entrada del diario
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryAction {
+    /// Se movió o copió (según el modo de la ejecución) a `new_path`
+    #[default]
+    Transfer,
+    /// Era un duplicado de `new_path` y se borró
+    Deleted,
+    /// Era un duplicado y se sustituyó por un enlace duro a `new_path`
+    HardLinked,
+    /// Era un duplicado y se sustituyó por un enlace simbólico a `new_path`
+    SymLinked,
+    /// Ya estaba en `original_path` antes de la ejecución y se apartó a `new_path`
+    /// para que otro archivo ocupara su sitio (sobrescribir un conflicto)
+    Replaced,
+}
+
+/// Registro de un archivo movido, copiado o sustituido durante una ejecución
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub original_path: PathBuf,
+    /// Destino del archivo o, para un duplicado sustituido, la copia que se conservó
+    pub new_path: PathBuf,
+    pub size: u64,
+    #[serde(default)]
+    pub action: EntryAction,
+    /// Hash del contenido de un duplicado, para restaurarlo solo si la copia
+    /// conservada no ha cambiado
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Fecha de modificación original de un duplicado sustituido
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<SystemTime>,
+    /// Destino original de un enlace simbólico trasladado, para recrearlo tal cual al deshacer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+}
+
+/// Diario de una ejecución completa, usado para poder deshacerla
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunJournal {
+    pub run_id: String,
+    /// Segundos desde UNIX_EPOCH
+    pub timestamp: u64,
+    pub mode: FileOperationMode,
+    pub root: PathBuf,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl RunJournal {
+    pub fn new(root: &Path, mode: FileOperationMode) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        RunJournal {
+            run_id: now.as_millis().to_string(),
+            timestamp: now.as_secs(),
+            mode,
+            root: root.to_path_buf(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, original_path: &Path, new_path: &Path, size: u64) {
+        self.record_link(original_path, new_path, size, None);
+    }
+
+    /// Como `record`; `link_target` es el destino que tenía el enlace simbólico trasladado
+    pub fn record_link(&mut self, original_path: &Path, new_path: &Path, size: u64, link_target: Option<&Path>) {
+        self.entries.push(JournalEntry {
+            original_path: original_path.to_path_buf(),
+            new_path: new_path.to_path_buf(),
+            size,
+            action: EntryAction::Transfer,
+            hash: None,
+            modified: None,
+            link_target: link_target.map(Path::to_path_buf),
+        });
+    }
+
+    /// Registra un archivo que había en `original_path` y se apartó a `aside`
+    pub fn record_set_aside(&mut self, original_path: &Path, aside: &Path, size: u64) {
+        self.entries.push(JournalEntry {
+            original_path: original_path.to_path_buf(),
+            new_path: aside.to_path_buf(),
+            size,
+            action: EntryAction::Replaced,
+            hash: None,
+            modified: None,
+            link_target: None,
+        });
+    }
+
+    /// Registra un duplicado sustituido: `duplicate` se puede restaurar
+    /// copiando `kept` mientras su contenido siga teniendo el mismo hash
+    pub fn record_replaced(
+        &mut self,
+        duplicate: &Path,
+        kept: &Path,
+        size: u64,
+        action: EntryAction,
+        hash: &str,
+        modified: Option<SystemTime>,
+    ) {
+        self.entries.push(JournalEntry {
+            original_path: duplicate.to_path_buf(),
+            new_path: kept.to_path_buf(),
+            size,
+            action,
+            hash: Some(hash.to_string()),
+            modified,
+            link_target: None,
+        });
+    }
+
+    /// Ruta del diario de `run_id`. El identificador solo puede tener dígitos,
+    /// así nunca sale de la carpeta de diarios.
+    fn file_path(organizer_path: &Path, run_id: &str) -> Result<PathBuf, OrganizerError> {
+        if !is_valid_run_id(run_id) {
+            return Err(OrganizerError::InvalidConfig(format!(
+                "Identificador de ejecución no válido '{}': solo puede tener dígitos",
+                run_id
+            )));
+        }
+        Ok(organizer_path.join(JOURNAL_DIR).join(format!("{}.json", run_id)))
+    }
+
+    /// Guarda el diario completo en `Organizer/.journal/<run_id>.json`. Se
+    /// escribe en un temporal y se renombra encima, así nunca queda a medias.
+    pub fn save(&self, organizer_path: &Path) -> Result<PathBuf, OrganizerError> {
+        let file_path = Self::file_path(organizer_path, &self.run_id)?;
+        let journal_dir = organizer_path.join(JOURNAL_DIR);
+        fs::create_dir_all(&journal_dir)
+            .map_err(|e| OrganizerError::io(Operation::CreateDir, &journal_dir, e))?;
+
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| OrganizerError::io(Operation::WriteJournal, &file_path, e.into()))?;
+        let partial = journal_dir.join(format!(".{}.json.tmp", self.run_id));
+        // El documento llega al disco antes de sustituir al diario que se iba escribiendo
+        File::create(&partial)
+            .and_then(|mut file| {
+                file.write_all(contents.as_bytes())?;
+                file.sync_data()
+            })
+            .and_then(|_| fs::rename(&partial, &file_path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&partial);
+                OrganizerError::io(Operation::WriteJournal, &file_path, e)
+            })?;
+
+        Ok(file_path)
+    }
+
+    /// Carga el diario de una ejecución concreta
+    pub fn load(organizer_path: &Path, run_id: &str) -> Result<Self, OrganizerError> {
+        let file_path = Self::file_path(organizer_path, run_id)?;
+        let contents = fs::read_to_string(&file_path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => OrganizerError::RunNotFound { run_id: run_id.to_string() },
+            _ => OrganizerError::io(Operation::ReadJournal, &file_path, e),
+        })?;
+        Self::parse(&contents).map_err(|message| OrganizerError::CorruptJournal { path: file_path, message })
+    }
+
+    /// Lee un diario terminado o, si la ejecución se cortó, el que se iba
+    /// escribiendo: una cabecera y una entrada por línea. Una última línea a
+    /// medias (el corte llegó mientras se escribía) se descarta.
+    fn parse(contents: &str) -> Result<Self, String> {
+        let complete_error = match serde_json::from_str::<RunJournal>(contents) {
+            Ok(journal) => return Ok(journal),
+            Err(e) => e.to_string(),
+        };
+
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().ok_or(complete_error.clone())?;
+        let mut journal = serde_json::from_str::<RunJournal>(header).map_err(|_| complete_error)?;
+        let lines: Vec<&str> = lines.collect();
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str::<JournalEntry>(line) {
+                Ok(entry) => journal.entries.push(entry),
+                Err(_) if i + 1 == lines.len() => break,
+                Err(e) => return Err(format!("línea {}: {}", i + 2, e)),
+            }
+        }
+        Ok(journal)
+    }
+
+    /// Lista los diarios guardados, del más antiguo al más reciente
+    pub fn list(organizer_path: &Path) -> Result<Vec<Self>, OrganizerError> {
+        let journal_dir = organizer_path.join(JOURNAL_DIR);
+        if !journal_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&journal_dir)
+            .map_err(|e| OrganizerError::io(Operation::ReadJournal, &journal_dir, e))?;
+
+        let mut journals = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json")
+                && let Ok(contents) = fs::read_to_string(&path)
+                && let Ok(journal) = Self::parse(&contents)
+                && is_valid_run_id(&journal.run_id)
+            {
+                journals.push(journal);
+            }
+        }
+
+        journals.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.run_id.cmp(&b.run_id)));
+        Ok(journals)
+    }
+
+    /// Elimina el diario del disco
+    pub fn remove(&self, organizer_path: &Path) -> Result<(), OrganizerError> {
+        let file_path = Self::file_path(organizer_path, &self.run_id)?;
+        fs::remove_file(&file_path)
+            .map_err(|e| OrganizerError::io(Operation::DeleteJournal, &file_path, e))?;
+
+        // La carpeta del diario solo se borra si ya no queda ningún diario
+        let _ = fs::remove_dir(organizer_path.join(JOURNAL_DIR));
+        Ok(())
+    }
+}
+
+/// Los identificadores son marcas de tiempo en milisegundos: solo dígitos
+fn is_valid_run_id(run_id: &str) -> bool {
+    !run_id.is_empty() && run_id.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Diario de una ejecución en curso. El archivo se crea antes de tocar nada y
+/// cada entrada se añade en cuanto termina su operación, así que si el proceso
+/// muere a mitad queda en disco todo lo hecho hasta entonces y se puede deshacer.
+#[derive(Debug)]
+pub struct JournalWriter {
+    journal: RunJournal,
+    organizer_path: PathBuf,
+    file: File,
+    /// `organizer_path` no existía; se borra al terminar si no se hizo nada
+    created_output: bool,
+    /// Archivos apartados hasta ahora con `set_aside`
+    set_aside: usize,
+}
+
+impl JournalWriter {
+    /// Crea el diario de una ejecución nueva. Si ya hay uno con el mismo
+    /// identificador (dos ejecuciones en el mismo milisegundo), usa el siguiente.
+    pub fn create(organizer_path: &Path, root: &Path, mode: FileOperationMode) -> Result<Self, OrganizerError> {
+        let created_output = !organizer_path.exists();
+        let journal_dir = organizer_path.join(JOURNAL_DIR);
+        fs::create_dir_all(&journal_dir)
+            .map_err(|e| OrganizerError::io(Operation::CreateDir, &journal_dir, e))?;
+
+        let mut journal = RunJournal::new(root, mode);
+        let mut id: u128 = journal.run_id.parse().unwrap_or_default();
+        let (file, file_path) = loop {
+            let file_path = RunJournal::file_path(organizer_path, &id.to_string())?;
+            match OpenOptions::new().write(true).create_new(true).open(&file_path) {
+                Ok(file) => break (file, file_path),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => id += 1,
+                Err(e) => return Err(OrganizerError::io(Operation::WriteJournal, &file_path, e)),
+            }
+        };
+        journal.run_id = id.to_string();
+
+        let mut writer = JournalWriter { journal, organizer_path: organizer_path.to_path_buf(), file, created_output, set_aside: 0 };
+        let header = serde_json::to_string(&writer.journal)
+            .map_err(|e| OrganizerError::io(Operation::WriteJournal, &file_path, e.into()))?;
+        writer.write_line(&header)?;
+        Ok(writer)
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.journal.run_id
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.journal.entries
+    }
+
+    /// Anota un archivo movido o copiado y lo escribe en el acto
+    pub fn record(&mut self, original_path: &Path, new_path: &Path, size: u64) -> Result<(), OrganizerError> {
+        self.record_link(original_path, new_path, size, None)
+    }
+
+    /// Como `record`, con el destino que tenía el enlace simbólico trasladado
+    pub fn record_link(
+        &mut self,
+        original_path: &Path,
+        new_path: &Path,
+        size: u64,
+        link_target: Option<&Path>,
+    ) -> Result<(), OrganizerError> {
+        self.journal.record_link(original_path, new_path, size, link_target);
+        self.append_last()?;
+        // Un archivo movido ya no está en su origen: sin su entrada en disco no se podría deshacer
+        if self.journal.mode == FileOperationMode::Cut {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Aparta el archivo que hay en `destination` a una carpeta de la ejecución
+    /// junto al diario, para que otro ocupe su sitio sin perderlo. Devuelve
+    /// dónde quedó; se anota con `record_set_aside` cuando el otro ya está en su sitio.
+    pub fn set_aside(&mut self, destination: &Path) -> Result<PathBuf, OrganizerError> {
+        self.set_aside += 1;
+        let folder = self.organizer_path
+            .join(JOURNAL_DIR)
+            .join(format!("{}.replaced", self.journal.run_id))
+            .join(self.set_aside.to_string());
+        fs::create_dir_all(&folder)
+            .map_err(|e| OrganizerError::io(Operation::CreateDir, &folder, e))?;
+
+        let aside = folder.join(destination.file_name().unwrap_or_default());
+        fs::rename(destination, &aside)
+            .map_err(|e| OrganizerError::io(Operation::Move, destination, e))?;
+        Ok(aside)
+    }
+
+    /// Devuelve a `destination` un archivo apartado cuyo sustituto no llegó a ocupar su sitio
+    pub fn put_back(&mut self, aside: &Path, destination: &Path) -> Result<(), OrganizerError> {
+        fs::rename(aside, destination)
+            .map_err(|e| OrganizerError::io(Operation::Restore, destination, e))?;
+        // La carpeta del apartado y la de la ejecución, si ya no tienen nada
+        for folder in aside.ancestors().skip(1).take(2) {
+            let _ = fs::remove_dir(folder);
+        }
+        Ok(())
+    }
+
+    /// Anota un archivo apartado con `set_aside` y lo escribe en el acto
+    pub fn record_set_aside(&mut self, original_path: &Path, aside: &Path, size: u64) -> Result<(), OrganizerError> {
+        self.journal.record_set_aside(original_path, aside, size);
+        self.append_last()?;
+        self.sync()
+    }
+
+    /// Anota un duplicado sustituido y lo escribe en el acto
+    pub fn record_replaced(
+        &mut self,
+        duplicate: &Path,
+        kept: &Path,
+        size: u64,
+        action: EntryAction,
+        hash: &str,
+        modified: Option<SystemTime>,
+    ) -> Result<(), OrganizerError> {
+        self.journal.record_replaced(duplicate, kept, size, action, hash, modified);
+        self.append_last()?;
+        self.sync()
+    }
+
+    /// Cierra el diario: lo deja como un documento completo y devuelve su
+    /// identificador, o lo borra si la ejecución no hizo nada
+    pub fn finish(self) -> Result<Option<String>, OrganizerError> {
+        // Lo escrito hasta aquí queda en disco aunque falle guardar el documento completo
+        self.sync()?;
+        let JournalWriter { journal, organizer_path, file, created_output, .. } = self;
+        drop(file);
+
+        if journal.entries.is_empty() {
+            let file_path = RunJournal::file_path(&organizer_path, &journal.run_id)?;
+            let _ = fs::remove_file(file_path);
+            let _ = fs::remove_dir(organizer_path.join(JOURNAL_DIR));
+            if created_output {
+                let _ = fs::remove_dir(&organizer_path);
+            }
+            return Ok(None);
+        }
+
+        journal.save(&organizer_path)?;
+        Ok(Some(journal.run_id))
+    }
+
+    fn append_last(&mut self) -> Result<(), OrganizerError> {
+        let Some(entry) = self.journal.entries.last() else {
+            return Ok(());
+        };
+        let line = serde_json::to_string(entry)
+            .map_err(|e| OrganizerError::io(Operation::WriteJournal, &self.organizer_path, e.into()))?;
+        self.write_line(&line)
+    }
+
+    fn sync(&self) -> Result<(), OrganizerError> {
+        let file_path = RunJournal::file_path(&self.organizer_path, &self.journal.run_id)?;
+        self.file
+            .sync_data()
+            .map_err(|e| OrganizerError::io(Operation::WriteJournal, &file_path, e))
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), OrganizerError> {
+        let file_path = RunJournal::file_path(&self.organizer_path, &self.journal.run_id)?;
+        self.file
+            .write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| self.file.flush())
+            .map_err(|e| OrganizerError::io(Operation::WriteJournal, &file_path, e))
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+use super::conflict::ConflictResolution;
+use super::error::OrganizerError;
+use super::FileOperationMode;
+
+/// Cómo terminó la operación de un archivo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeStatus {
+    /// El archivo se movió o copió
+    Done,
+    /// Se dejó donde estaba por la política de conflictos
+    Skipped,
+    /// La operación falló; el motivo está en `error`
+    Failed,
+    /// Se canceló la ejecución a mitad de la transferencia; el origen sigue en su sitio
+    Cancelled,
+}
+
+impl OutcomeStatus {
+    /// Nombre estable, el mismo que en JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutcomeStatus::Done => "done",
+            OutcomeStatus::Skipped => "skipped",
+            OutcomeStatus::Failed => "failed",
+            OutcomeStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for OutcomeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            OutcomeStatus::Done => "hecho",
+            OutcomeStatus::Skipped => "omitido",
+            OutcomeStatus::Failed => "fallido",
+            OutcomeStatus::Cancelled => "cancelado",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Lo que pasó con un archivo del plan al ejecutarlo
+#[derive(Debug, Serialize)]
+pub struct FileOutcome {
+    pub source: PathBuf,
+    /// Destino final, ya resuelto el conflicto; si el archivo no se tocó, el planificado
+    pub destination: PathBuf,
+    /// Carpeta elegida por la estrategia, relativa a la de destino
+    pub folder: String,
+    pub action: FileOperationMode,
+    /// Bytes transferidos (0 si no se llegó a transferir)
+    pub bytes: u64,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    pub status: OutcomeStatus,
+    /// Cómo se resolvió el conflicto, si el destino ya existía
+    pub conflict: Option<ConflictResolution>,
+    pub error: Option<OrganizerError>,
+}
+
+impl FileOutcome {
+    pub fn is_done(&self) -> bool {
+        self.status == OutcomeStatus::Done
+    }
+
+    /// Nombre con el que quedó el archivo
+    pub fn file_name(&self) -> String {
+        self.destination
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "archivo".to_string())
+    }
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
+
+/// Nombres de los archivos transferidos, agrupados por carpeta de destino
+pub fn extension_map(outcomes: &[FileOutcome]) -> HashMap<String, Vec<String>> {
+    let mut map: HashMap<String, Vec<String>> = HashMap::new();
+    for outcome in outcomes.iter().filter(|o| o.is_done()) {
+        map.entry(outcome.folder.clone()).or_default().push(outcome.file_name());
+    }
+    map
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::{CancelToken, ConflictPolicy, FileOperationMode, IgnoredPath, TypeMismatch, UnreadablePath};
+
+/// Conflicto detectado al planificar una operación
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum PlanConflict {
+    /// Ya existe un archivo en la ruta de destino
+    DestinationExists,
+    /// Otro archivo del mismo plan tiene el mismo destino
+    DuplicateDestination(PathBuf),
+}
+
+impl fmt::Display for PlanConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanConflict::DestinationExists => write!(f, "el destino ya existe"),
+            PlanConflict::DuplicateDestination(other) => {
+                write!(f, "mismo destino que {}", other.display())
+            }
+        }
+    }
+}
+
+/// Operación individual de un plan de organización
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedOperation {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub action: FileOperationMode,
+    /// Carpeta de destino dentro de `Organizer/` (p. ej. `PDF`)
+    pub folder_name: String,
+    /// Motivo por el que el archivo va a esa carpeta
+    pub reason: String,
+    /// Tamaño al planificar, para estimar el avance
+    pub size: u64,
+    pub conflicts: Vec<PlanConflict>,
+}
+
+impl PlannedOperation {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+/// Archivo que no se va a mover y el motivo
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Plan completo de organización, calculado sin efectos sobre el disco
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizePlan {
+    pub root: PathBuf,
+    pub organizer_path: PathBuf,
+    pub mode: FileOperationMode,
+    /// Política que se aplicará a los conflictos al ejecutar el plan
+    pub conflict_policy: ConflictPolicy,
+    pub verify_transfers: bool,
+    /// Archivos que se transfieren a la vez
+    pub jobs: usize,
+    pub operations: Vec<PlannedOperation>,
+    /// Archivos cuya extensión no corresponde con su contenido
+    pub type_mismatches: Vec<TypeMismatch>,
+    /// Archivos que se quedan en su sitio: los que la estrategia no mueve, los
+    /// enlaces que no se siguen y lo que no es un archivo normal (sockets, FIFO...)
+    pub skipped: Vec<SkippedFile>,
+    /// Elementos que no se recorrieron por un `.organizerignore`
+    pub ignored: Vec<IgnoredPath>,
+    /// Archivos descartados por el filtro de tamaño, fecha, nombre u ocultos
+    pub filtered: Vec<PathBuf>,
+    /// Carpetas que no se pudieron recorrer; su contenido no está en el plan
+    pub unreadable: Vec<UnreadablePath>,
+    /// Se canceló mientras se recorría la carpeta; el plan no tiene operaciones
+    pub cancelled: bool,
+    /// Token que detiene la ejecución del plan entre archivos
+    #[serde(skip)]
+    pub cancel: CancelToken,
+}
+
+impl OrganizePlan {
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn conflict_count(&self) -> usize {
+        self.operations.iter().filter(|op| op.has_conflicts()).count()
+    }
+}
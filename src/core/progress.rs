@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Avance de una ejecución, enviado antes de procesar cada archivo
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Número del archivo que se va a procesar, desde 1
+    pub current: usize,
+    pub total: usize,
+    /// Archivo que se va a procesar
+    pub file: PathBuf,
+    /// Bytes de los archivos ya procesados, se hayan transferido o no
+    pub bytes_done: u64,
+    /// Bytes de todos los archivos del plan
+    pub bytes_total: u64,
+    /// Tiempo desde que empezó la ejecución
+    pub elapsed: Duration,
+    /// `total` y `bytes_total` son estimaciones porque el recorrido aún no ha
+    /// terminado (organización en streaming)
+    pub estimated: bool,
+}
+
+impl Progress {
+    /// Fracción completada entre 0 y 1, por bytes (o por archivos si todos están vacíos)
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total > 0 {
+            self.bytes_done as f32 / self.bytes_total as f32
+        } else if self.total > 0 {
+            self.current.saturating_sub(1) as f32 / self.total as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Tiempo restante estimado al ritmo actual; `None` hasta tener algo medido
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction() as f64;
+        if fraction <= 0.0 || self.elapsed.is_zero() {
+            return None;
+        }
+        Some(self.elapsed.mul_f64((1.0 - fraction) / fraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(current: usize, total: usize) -> Progress {
+        Progress {
+            current,
+            total,
+            file: PathBuf::new(),
+            bytes_done: 0,
+            bytes_total: 0,
+            elapsed: Duration::ZERO,
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn fraction_by_files_starts_at_zero() {
+        assert_eq!(progress(0, 4).fraction(), 0.0);
+        assert_eq!(progress(1, 4).fraction(), 0.0);
+        assert_eq!(progress(3, 4).fraction(), 0.5);
+        assert_eq!(progress(0, 0).fraction(), 0.0);
+    }
+}
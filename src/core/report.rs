@@ -0,0 +1,585 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use super::dupes::{DuplicateAction, DuplicateGroup, DuplicateReport, KeepRule};
+use super::error::{Operation, OrganizerError};
+use super::filter::format_size;
+use super::outcome::{extension_map, FileOutcome, OutcomeStatus};
+use super::plan::{OrganizePlan, PlannedOperation};
+use super::scan::{count_by_ignore_file, UnreadablePath};
+use super::{DuplicateResolutionResult, FileOperationMode, OrganizerResult, UndoResult};
+
+/// Formato de un informe de ejecución
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    /// Una fila por archivo
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub const ALL: [ReportFormat; 4] = [
+        ReportFormat::Json,
+        ReportFormat::Csv,
+        ReportFormat::Markdown,
+        ReportFormat::Html,
+    ];
+
+    /// Nombre usado en la CLI (`--report-format`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+
+    /// Formato que corresponde a la extensión del archivo, si se reconoce
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "markdown" => Some(ReportFormat::Markdown),
+            "htm" => Some(ReportFormat::Html),
+            other => other.parse().ok(),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReportFormat::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| format!("Formato de informe desconocido '{}' (json, csv, md, html)", s))
+    }
+}
+
+/// Informe de una ejecución: el resultado más la carpeta, el modo y la fecha
+#[derive(Debug, Serialize)]
+pub struct RunReport<'a> {
+    pub root: &'a Path,
+    pub mode: FileOperationMode,
+    pub generated_at: DateTime<Local>,
+    #[serde(flatten)]
+    pub result: &'a OrganizerResult,
+}
+
+impl<'a> RunReport<'a> {
+    pub fn new(root: &'a Path, mode: FileOperationMode, result: &'a OrganizerResult) -> Self {
+        RunReport { root, mode, generated_at: Local::now(), result }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            // Todo lo que contiene el resultado se puede serializar
+            ReportFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+            ReportFormat::Csv => self.csv(),
+            ReportFormat::Markdown => self.markdown(),
+            ReportFormat::Html => self.html(),
+        }
+    }
+
+    /// Totales del informe, sin la lista de archivos
+    pub fn summary(&self) -> RunSummary<'a> {
+        let outcomes = &self.result.outcomes;
+        let count = |status| outcomes.iter().filter(|o| o.status == status).count();
+        RunSummary {
+            root: self.root,
+            mode: self.mode,
+            run_id: self.result.run_id.as_deref(),
+            done: count(OutcomeStatus::Done),
+            skipped: count(OutcomeStatus::Skipped),
+            failed: count(OutcomeStatus::Failed),
+            cancelled_files: count(OutcomeStatus::Cancelled),
+            bytes: outcomes.iter().filter(|o| o.is_done()).map(|o| o.bytes).sum(),
+            folders_created: self.result.folders_created,
+            conflicts: self.result.conflicts.len(),
+            left_in_place: self.result.skipped.len(),
+            ignored: self.result.ignored.len(),
+            filtered: self.result.filtered.len(),
+            unreadable: &self.result.unreadable,
+            errors: self.result.errors.as_deref().unwrap_or_default(),
+            cancelled: self.result.cancelled,
+        }
+    }
+
+    /// Escribe el informe en `path`
+    pub fn write(&self, path: &Path, format: ReportFormat) -> Result<(), OrganizerError> {
+        fs::write(path, self.render(format)).map_err(|e| OrganizerError::io(Operation::WriteFile, path, e))
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(self.root).unwrap_or(path).display().to_string()
+    }
+
+    fn mode_name(&self) -> &'static str {
+        match self.mode {
+            FileOperationMode::Cut => "mover",
+            FileOperationMode::Copy => "copiar",
+        }
+    }
+
+    /// Contadores de la cabecera, en el orden en que se muestran
+    fn totals(&self) -> Vec<(&'static str, String)> {
+        let outcomes = &self.result.outcomes;
+        let count = |status| outcomes.iter().filter(|o| o.status == status).count().to_string();
+        let bytes = outcomes.iter().filter(|o| o.is_done()).map(|o| o.bytes).sum();
+
+        vec![
+            ("Archivos transferidos", count(OutcomeStatus::Done)),
+            ("Tamaño transferido", format_size(bytes)),
+            ("Archivos omitidos", count(OutcomeStatus::Skipped)),
+            ("Archivos con errores", count(OutcomeStatus::Failed)),
+            ("Archivos cancelados", count(OutcomeStatus::Cancelled)),
+            ("Carpetas creadas", self.result.folders_created.to_string()),
+            ("Conflictos", self.result.conflicts.len().to_string()),
+            ("Dejados en su sitio por la estrategia", self.result.skipped.len().to_string()),
+            ("Ignorados por .organizerignore", self.result.ignored.len().to_string()),
+            ("Descartados por los filtros", self.result.filtered.len().to_string()),
+            ("Rutas sin leer", self.result.unreadable.len().to_string()),
+        ]
+    }
+
+    /// Filas de la tabla de archivos: estado, origen, destino, tamaño y detalle
+    fn rows(&self) -> Vec<[String; 5]> {
+        self.result
+            .outcomes
+            .iter()
+            .map(|outcome| {
+                let detail = match (&outcome.error, &outcome.conflict) {
+                    (Some(error), _) => error.to_string(),
+                    (None, Some(conflict)) => format!("conflicto: {}", conflict),
+                    (None, None) => String::new(),
+                };
+                [
+                    outcome.status.to_string(),
+                    self.relative(&outcome.source),
+                    self.relative(&outcome.destination),
+                    format_size(outcome.bytes),
+                    detail,
+                ]
+            })
+            .collect()
+    }
+
+    /// Errores generales, incidencias de tipo y `.organizerignore` como líneas sueltas
+    fn notes(&self) -> Vec<(&'static str, Vec<String>)> {
+        let result = self.result;
+        vec![
+            (
+                "Errores",
+                result.errors.iter().flatten().map(ToString::to_string).collect(),
+            ),
+            (
+                "Rutas sin leer",
+                result.unreadable.iter().map(|dir| dir.error.clone()).collect(),
+            ),
+            (
+                "Dejados en su sitio",
+                result.skipped
+                    .iter()
+                    .map(|file| format!("{}: {}", self.relative(&file.path), file.reason))
+                    .collect(),
+            ),
+            (
+                "Extension distinta al contenido",
+                result.type_mismatches
+                    .iter()
+                    .map(|m| format!("{}: .{} pero parece .{}", self.relative(&m.path), m.extension, m.detected.extension))
+                    .collect(),
+            ),
+            (
+                "Ignorados por .organizerignore",
+                count_by_ignore_file(&result.ignored)
+                    .into_iter()
+                    .map(|(file, count)| format!("{}: {} elemento(s)", self.relative(file), count))
+                    .collect(),
+            ),
+        ]
+    }
+
+    fn csv(&self) -> String {
+        let mut out = String::from("source,destination,folder,action,status,bytes,duration_ms,conflict,error\n");
+        for outcome in &self.result.outcomes {
+            let fields = [
+                outcome.source.display().to_string(),
+                outcome.destination.display().to_string(),
+                outcome.folder.clone(),
+                self.action_name(outcome.action).to_string(),
+                outcome.status.name().to_string(),
+                outcome.bytes.to_string(),
+                outcome.duration.as_millis().to_string(),
+                outcome.conflict.as_ref().map(ToString::to_string).unwrap_or_default(),
+                outcome.error.as_ref().map(ToString::to_string).unwrap_or_default(),
+            ];
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn action_name(&self, action: FileOperationMode) -> &'static str {
+        match action {
+            FileOperationMode::Cut => "move",
+            FileOperationMode::Copy => "copy",
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = String::from("# Informe de organizacion\n\n");
+        out.push_str(&format!("- Carpeta: `{}`\n", self.root.display()));
+        out.push_str(&format!("- Modo: {}\n", self.mode_name()));
+        out.push_str(&format!("- Fecha: {}\n", self.generated_at.format("%Y-%m-%d %H:%M:%S")));
+        if let Some(run_id) = &self.result.run_id {
+            out.push_str(&format!("- Ejecucion: `{}`\n", run_id));
+        }
+        if self.result.cancelled {
+            out.push_str("- Estado: **cancelada** antes de terminar\n");
+        }
+
+        out.push_str("\n## Resumen\n\n| | |\n|---|---:|\n");
+        for (label, value) in self.totals() {
+            out.push_str(&format!("| {} | {} |\n", label, value));
+        }
+
+        let mut folders: Vec<_> = extension_map(&self.result.outcomes).into_iter().collect();
+        folders.sort();
+        if !folders.is_empty() {
+            out.push_str("\n## Carpetas\n\n| Carpeta | Archivos |\n|---|---:|\n");
+            for (folder, files) in folders {
+                out.push_str(&format!("| {} | {} |\n", markdown_cell(&folder), files.len()));
+            }
+        }
+
+        let rows = self.rows();
+        if !rows.is_empty() {
+            out.push_str("\n## Archivos\n\n| Estado | Origen | Destino | Tamaño | Detalle |\n|---|---|---|---:|---|\n");
+            for row in rows {
+                let cells: Vec<String> = row.iter().map(|cell| markdown_cell(cell)).collect();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+        }
+
+        for (title, lines) in self.notes() {
+            if lines.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n## {}\n\n", title));
+            for line in lines {
+                out.push_str(&format!("- {}\n", line));
+            }
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Informe de organizacion</title>\n\
+             <style>body{font-family:sans-serif}table{border-collapse:collapse}\
+             td,th{border:1px solid #ccc;padding:2px 8px;text-align:left}</style>\n</head>\n<body>\n\
+             <h1>Informe de organizacion</h1>\n<ul>\n",
+        );
+        out.push_str(&format!("<li>Carpeta: <code>{}</code></li>\n", html_escape(&self.root.display().to_string())));
+        out.push_str(&format!("<li>Modo: {}</li>\n", self.mode_name()));
+        out.push_str(&format!("<li>Fecha: {}</li>\n", self.generated_at.format("%Y-%m-%d %H:%M:%S")));
+        if let Some(run_id) = &self.result.run_id {
+            out.push_str(&format!("<li>Ejecucion: <code>{}</code></li>\n", html_escape(run_id)));
+        }
+        if self.result.cancelled {
+            out.push_str("<li>Estado: <strong>cancelada</strong> antes de terminar</li>\n");
+        }
+        out.push_str("</ul>\n<h2>Resumen</h2>\n<table>\n");
+        for (label, value) in self.totals() {
+            out.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", label, html_escape(&value)));
+        }
+        out.push_str("</table>\n");
+
+        let rows = self.rows();
+        if !rows.is_empty() {
+            out.push_str("<h2>Archivos</h2>\n<table>\n<tr><th>Estado</th><th>Origen</th><th>Destino</th><th>Tamaño</th><th>Detalle</th></tr>\n");
+            for row in rows {
+                let cells: String = row.iter().map(|cell| format!("<td>{}</td>", html_escape(cell))).collect();
+                out.push_str(&format!("<tr>{}</tr>\n", cells));
+            }
+            out.push_str("</table>\n");
+        }
+
+        for (title, lines) in self.notes() {
+            if lines.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("<h2>{}</h2>\n<ul>\n", title));
+            for line in lines {
+                out.push_str(&format!("<li>{}</li>\n", html_escape(&line)));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// Totales de una ejecución, el último evento de la salida NDJSON
+#[derive(Debug, Serialize)]
+pub struct RunSummary<'a> {
+    pub root: &'a Path,
+    pub mode: FileOperationMode,
+    pub run_id: Option<&'a str>,
+    pub done: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// Archivos cuya transferencia se interrumpió al cancelar
+    pub cancelled_files: usize,
+    pub bytes: u64,
+    pub folders_created: usize,
+    pub conflicts: usize,
+    /// Dejados en su sitio por la estrategia
+    pub left_in_place: usize,
+    pub ignored: usize,
+    pub filtered: usize,
+    /// Carpetas que no se pudieron recorrer
+    pub unreadable: &'a [UnreadablePath],
+    /// Errores que no son de un archivo concreto
+    pub errors: &'a [OrganizerError],
+    /// La ejecución se detuvo antes de terminar el plan
+    pub cancelled: bool,
+}
+
+/// Evento de la salida NDJSON, una línea por evento con su tipo en `event`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent<'a> {
+    /// Operación de un plan que no se ejecuta (`--dry-run`)
+    Planned(&'a PlannedOperation),
+    /// Totales del plan, tras sus operaciones
+    Plan {
+        operations: usize,
+        conflicts: usize,
+        left_in_place: usize,
+        ignored: usize,
+        filtered: usize,
+        unreadable: usize,
+    },
+    /// Un archivo recién procesado
+    File(&'a FileOutcome),
+    Summary(RunSummary<'a>),
+    /// Error que detuvo la ejecución
+    Error(&'a OrganizerError),
+    /// Un grupo de `dupes`; `kept` es la copia que se conserva si se eligió una acción
+    Duplicate {
+        #[serde(flatten)]
+        group: &'a DuplicateGroup,
+        kept: Option<&'a Path>,
+    },
+    /// Totales de la búsqueda de duplicados, tras sus grupos
+    Duplicates {
+        root: &'a Path,
+        groups: usize,
+        duplicate_files: usize,
+        files_scanned: usize,
+        reclaimable_bytes: u64,
+        errors: &'a [OrganizerError],
+    },
+    /// Lo que se hizo con las copias sobrantes (`dupes --action`)
+    Resolved(&'a DuplicateResolutionResult),
+    /// Resultado de `undo`
+    Undo(&'a UndoResult),
+}
+
+impl<'a> RunEvent<'a> {
+    pub fn plan(plan: &OrganizePlan) -> Self {
+        RunEvent::Plan {
+            operations: plan.operations.len(),
+            conflicts: plan.conflict_count(),
+            left_in_place: plan.skipped.len(),
+            ignored: plan.ignored.len(),
+            filtered: plan.filtered.len(),
+            unreadable: plan.unreadable.len(),
+        }
+    }
+
+    pub fn duplicates(report: &'a DuplicateReport) -> Self {
+        RunEvent::Duplicates {
+            root: &report.root,
+            groups: report.groups.len(),
+            duplicate_files: report.duplicate_files(),
+            files_scanned: report.files_scanned,
+            reclaimable_bytes: report.reclaimable_bytes,
+            errors: &report.errors,
+        }
+    }
+
+    /// El evento como una línea JSON, sin el salto final
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Salida de `dupes` con `--output-format json`: el informe, la copia que se
+/// conserva de cada grupo si se eligió una acción y lo que se hizo con las demás
+#[derive(Debug, Serialize)]
+pub struct DuplicatesDocument<'a> {
+    #[serde(flatten)]
+    pub report: &'a DuplicateReport,
+    /// Acción elegida con `--action`, por su nombre en la CLI
+    pub action: Option<&'static str>,
+    /// Copia conservada de cada grupo, en el orden de `groups`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kept: Vec<&'a Path>,
+    /// `None` sin acción o con `--dry-run`
+    pub resolution: Option<&'a DuplicateResolutionResult>,
+}
+
+impl<'a> DuplicatesDocument<'a> {
+    pub fn new(
+        report: &'a DuplicateReport,
+        resolution: Option<(&KeepRule, DuplicateAction)>,
+        result: Option<&'a DuplicateResolutionResult>,
+    ) -> Self {
+        let kept = match resolution {
+            Some((keep, _)) => {
+                let keep = keep.within(&report.root);
+                report.groups.iter().map(|group| group.keeper(&keep)).collect()
+            }
+            None => Vec::new(),
+        };
+        DuplicatesDocument {
+            report,
+            action: resolution.map(|(_, action)| action.name()),
+            kept,
+            resolution: result,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Entre comillas si contiene comas, comillas o saltos de línea
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Sin `|` ni saltos de línea, que romperían la tabla
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Nombre con todo lo que puede romper un CSV, una tabla Markdown o el HTML
+    const HOSTILE: &str = "a,\"b\" | <script>&\nc.txt";
+
+    fn outcome(name: &str, status: OutcomeStatus) -> FileOutcome {
+        FileOutcome {
+            source: PathBuf::from("/raiz").join(name),
+            destination: PathBuf::from("/raiz/Organizer/TXT").join(name),
+            folder: "TXT".to_string(),
+            action: FileOperationMode::Cut,
+            bytes: 3,
+            duration: Duration::from_millis(5),
+            status,
+            conflict: None,
+            error: None,
+        }
+    }
+
+    fn result() -> OrganizerResult {
+        OrganizerResult {
+            total_moved: 1,
+            folders_created: 1,
+            extension_map: Default::default(),
+            outcomes: vec![outcome(HOSTILE, OutcomeStatus::Done), outcome("b.txt", OutcomeStatus::Cancelled)],
+            summary: String::new(),
+            errors: None,
+            run_id: Some("1".to_string()),
+            conflicts: Vec::new(),
+            type_mismatches: Vec::new(),
+            skipped: Vec::new(),
+            ignored: Vec::new(),
+            filtered: Vec::new(),
+            unreadable: Vec::new(),
+            cancelled: true,
+        }
+    }
+
+    fn render(format: ReportFormat) -> String {
+        let result = result();
+        RunReport::new(Path::new("/raiz"), FileOperationMode::Cut, &result).render(format)
+    }
+
+    #[test]
+    fn json_keeps_the_name_intact() {
+        let json: serde_json::Value = serde_json::from_str(&render(ReportFormat::Json)).unwrap();
+        assert_eq!(json["outcomes"][0]["source"], format!("/raiz/{}", HOSTILE));
+        assert_eq!(json["outcomes"][1]["status"], "cancelled");
+    }
+
+    #[test]
+    fn csv_quotes_commas_quotes_and_newlines() {
+        let csv = render(ReportFormat::Csv);
+        let quoted = format!("\"/raiz/{}\"", HOSTILE.replace('"', "\"\""));
+        assert!(csv.starts_with(&format!("source,destination,folder,action,status,bytes,duration_ms,conflict,error\n{},", quoted)));
+        assert_eq!(csv_field("plain"), "plain");
+    }
+
+    #[test]
+    fn markdown_escapes_table_separators() {
+        let markdown = render(ReportFormat::Markdown);
+        let row = markdown.lines().find(|line| line.starts_with("| hecho |")).unwrap();
+        assert!(row.contains("a,\"b\" \\| <script>& c.txt"), "{}", row);
+        assert_eq!(row.matches(" | ").count(), 4);
+        assert!(markdown.contains("| Archivos cancelados | 1 |"));
+    }
+
+    #[test]
+    fn html_escapes_file_names() {
+        let html = render(ReportFormat::Html);
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("a,&quot;b&quot; | &lt;script&gt;&amp;"));
+        assert!(html.contains("<tr><th>Archivos cancelados</th><td>1</td></tr>"));
+    }
+
+    #[test]
+    fn summary_counts_cancelled_files() {
+        let result = result();
+        let summary = RunReport::new(Path::new("/raiz"), FileOperationMode::Cut, &result).summary();
+        assert_eq!((summary.done, summary.cancelled_files, summary.cancelled), (1, 1, true));
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::{Operation, OrganizerError};
+
+/// Categoría definida por el usuario: qué archivos agrupa y en qué carpeta
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryRule {
+    pub name: String,
+    /// Carpeta de destino; si no se indica se usa `name`
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Extensiones sin punto (`jpg`, `jpeg`...)
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Tipos MIME, admite comodín de subtipo (`image/*`)
+    #[serde(default)]
+    pub mime_types: Vec<String>,
+    /// Patrones sobre el nombre del archivo con `*` y `?` (`IMG_*`)
+    #[serde(default)]
+    pub name_patterns: Vec<String>,
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    #[serde(default)]
+    pub max_size: Option<u64>,
+}
+
+impl CategoryRule {
+    fn new(name: &str, extensions: &[&str]) -> Self {
+        CategoryRule {
+            name: name.to_string(),
+            folder: None,
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            mime_types: Vec::new(),
+            name_patterns: Vec::new(),
+            min_size: None,
+            max_size: None,
+        }
+    }
+
+    pub fn folder_name(&self) -> &str {
+        self.folder.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Indica si un archivo pertenece a la categoría. Basta con que coincida una
+    /// extensión, un tipo MIME o un patrón, y además el tamaño debe estar en rango.
+    pub fn matches(&self, file_name: &str, extension: Option<&str>, mime_type: Option<&str>, size: u64) -> bool {
+        if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max) {
+            return false;
+        }
+
+        let has_criteria = !self.extensions.is_empty()
+            || !self.mime_types.is_empty()
+            || !self.name_patterns.is_empty();
+        if !has_criteria {
+            // Una regla solo con tamaños agrupa todo lo que esté en rango
+            return self.min_size.is_some() || self.max_size.is_some();
+        }
+
+        let by_extension = extension.is_some_and(|ext| {
+            self.extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(ext))
+        });
+        let by_mime = mime_type.is_some_and(|mime| {
+            self.mime_types.iter().any(|pattern| mime_matches(pattern, mime))
+        });
+        let by_name = self.name_patterns
+            .iter()
+            .any(|pattern| wildcard_match(&pattern.to_lowercase(), &file_name.to_lowercase()));
+
+        by_extension || by_mime || by_name
+    }
+}
+
+/// Conjunto de categorías; la primera que coincide decide la carpeta
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub categories: Vec<CategoryRule>,
+    /// Carpeta para los archivos que no encajan en ninguna categoría.
+    /// Si no se indica, se agrupan por extensión como siempre.
+    #[serde(default)]
+    pub fallback: Option<String>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::builtin()
+    }
+}
+
+impl RuleSet {
+    /// Categorías incluidas por defecto
+    pub fn builtin() -> Self {
+        RuleSet {
+            categories: vec![
+                CategoryRule::new("Imagenes", &["jpg", "jpeg", "png", "gif", "bmp", "webp", "svg", "tif", "tiff", "heic", "ico", "raw"]),
+                CategoryRule::new("Documentos", &["pdf", "doc", "docx", "odt", "rtf", "txt", "md", "epub", "ppt", "pptx", "odp"]),
+                CategoryRule::new("Hojas_de_Calculo", &["xls", "xlsx", "ods", "csv", "tsv"]),
+                CategoryRule::new("Audio", &["mp3", "wav", "flac", "ogg", "m4a", "aac", "wma", "opus"]),
+                CategoryRule::new("Video", &["mp4", "mkv", "avi", "mov", "wmv", "webm", "flv", "m4v"]),
+                CategoryRule::new("Comprimidos", &["zip", "rar", "7z", "tar", "gz", "bz2", "xz", "tgz", "zst"]),
+                CategoryRule::new("Codigo", &["rs", "py", "js", "ts", "java", "c", "cpp", "h", "hpp", "go", "rb", "php", "sh", "html", "css", "json", "toml", "yaml", "yml", "xml"]),
+                CategoryRule::new("Ejecutables", &["exe", "msi", "deb", "rpm", "appimage", "dmg", "apk", "bin"]),
+            ],
+            fallback: None,
+        }
+    }
+
+    /// Carga un conjunto de reglas desde un archivo TOML o JSON (según la extensión)
+    pub fn load(path: &Path) -> Result<Self, OrganizerError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| OrganizerError::io(Operation::ReadFile, path, e))?;
+
+        let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let rules: RuleSet = if is_json {
+            serde_json::from_str(&contents).map_err(|e| {
+                OrganizerError::InvalidConfig(format!("Reglas JSON no válidas en '{}': {}", path.display(), e))
+            })?
+        } else {
+            toml::from_str(&contents).map_err(|e| {
+                OrganizerError::InvalidConfig(format!("Reglas TOML no válidas en '{}': {}", path.display(), e))
+            })?
+        };
+
+        rules.validate().map_err(OrganizerError::InvalidConfig)?;
+        Ok(rules)
+    }
+
+    /// Comprueba que las carpetas de destino sean nombres simples
+    pub fn validate(&self) -> Result<(), String> {
+        let folders = self.categories
+            .iter()
+            .map(|rule| rule.folder_name())
+            .chain(self.fallback.as_deref());
+
+        for folder in folders {
+            if folder.trim().is_empty() || folder.contains(['/', '\\']) || folder == "." || folder == ".." {
+                return Err(format!("Carpeta de categoría no válida: '{}'", folder));
+            }
+        }
+        Ok(())
+    }
+
+    /// Devuelve la primera categoría que encaja con el archivo
+    pub fn classify(&self, file_name: &str, extension: Option<&str>, mime_type: Option<&str>, size: u64) -> Option<&CategoryRule> {
+        self.categories
+            .iter()
+            .find(|rule| rule.matches(file_name, extension, mime_type, size))
+    }
+}
+
+/// Tipo MIME aproximado a partir de la extensión
+pub fn mime_from_extension(extension: &str) -> Option<&'static str> {
+    let mime = match extension.to_lowercase().as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "tif" | "tiff" => "image/tiff",
+        "heic" => "image/heic",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        "rtf" => "application/rtf",
+        "epub" => "application/epub+zip",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "xml" => "application/xml",
+        "json" => "application/json",
+        "js" => "text/javascript",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "ogg" | "opus" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        "aac" => "audio/aac",
+        "mp4" | "m4v" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "avi" => "video/x-msvideo",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        "zip" => "application/zip",
+        "rar" => "application/vnd.rar",
+        "7z" => "application/x-7z-compressed",
+        "tar" => "application/x-tar",
+        "gz" | "tgz" => "application/gzip",
+        "bz2" => "application/x-bzip2",
+        "xz" => "application/x-xz",
+        "exe" | "msi" => "application/x-msdownload",
+        "deb" => "application/vnd.debian.binary-package",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// `image/*` coincide con cualquier `image/...`
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime
+            .split_once('/')
+            .is_some_and(|(kind, _)| kind.eq_ignore_ascii_case(prefix)),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+/// Comodines simples: `*` cualquier secuencia, `?` un carácter
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::testing::TempDir;
+
+    fn photos() -> RuleSet {
+        RuleSet {
+            categories: vec![CategoryRule {
+                folder: Some("Fotos".to_string()),
+                mime_types: vec!["image/*".to_string()],
+                name_patterns: vec!["IMG_*".to_string()],
+                min_size: Some(10),
+                ..CategoryRule::new("Imagenes", &["JPG", ".png"])
+            }],
+            fallback: Some("Otros".to_string()),
+        }
+    }
+
+    #[test]
+    fn load_round_trips_toml_and_json() {
+        let dir = TempDir::new("rules-load");
+        let rules = photos();
+        let toml_path = dir.write("rules.toml", toml::to_string(&rules).unwrap());
+        let json_path = dir.write("rules.JSON", serde_json::to_string(&rules).unwrap());
+
+        assert_eq!(RuleSet::load(&toml_path).unwrap(), rules);
+        assert_eq!(RuleSet::load(&json_path).unwrap(), rules);
+    }
+
+    #[test]
+    fn invalid_rule_files_are_rejected() {
+        let dir = TempDir::new("rules-invalid");
+        let broken = dir.write("broken.toml", "[[categories]\nname = ");
+        assert!(matches!(RuleSet::load(&broken), Err(OrganizerError::InvalidConfig(_))));
+
+        let escaping = dir.write("escaping.json", r#"{"categories": [{"name": "x", "folder": "../fuera"}]}"#);
+        assert!(matches!(RuleSet::load(&escaping), Err(OrganizerError::InvalidConfig(_))));
+
+        assert!(RuleSet::load(&dir.join("missing.toml")).is_err());
+    }
+
+    #[test]
+    fn validate_requires_simple_folder_names() {
+        assert!(RuleSet::builtin().validate().is_ok());
+        for folder in ["", " ", ".", "..", "a/b", "a\\b"] {
+            let rules = RuleSet { fallback: Some(folder.to_string()), ..RuleSet::builtin() };
+            assert!(rules.validate().is_err(), "{:?}", folder);
+        }
+    }
+
+    #[test]
+    fn rule_matches_extension_mime_or_name_within_size() {
+        let rules = photos();
+        let rule = &rules.categories[0];
+        assert!(rule.matches("a.jpg", Some("jpg"), None, 100));
+        assert!(rule.matches("a.PNG", Some("PNG"), None, 100));
+        assert!(rule.matches("a.webp", Some("webp"), Some("image/webp"), 100));
+        assert!(rule.matches("img_001", None, None, 100));
+        assert!(!rule.matches("a.txt", Some("txt"), Some("text/plain"), 100));
+        // Fuera del rango de tamaño no cuenta ningún otro criterio
+        assert!(!rule.matches("a.jpg", Some("jpg"), None, 9));
+
+        let only_size = CategoryRule { max_size: Some(5), ..CategoryRule::new("Pequenos", &[]) };
+        assert!(only_size.matches("a.bin", None, None, 5));
+        assert!(!only_size.matches("a.bin", None, None, 6));
+        assert!(!CategoryRule::new("Vacia", &[]).matches("a.bin", None, None, 0));
+
+        assert_eq!(RuleSet::builtin().classify("A.JPEG", Some("JPEG"), None, 1).map(|r| r.name.as_str()), Some("Imagenes"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("img_*", "img_001.jpg"));
+        assert!(wildcard_match("*.t?t", "notes.txt"));
+        assert!(wildcard_match("*a*b*", "xxaxxbxx"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("?", ""));
+        assert!(!wildcard_match("*.txt", "notes.txt.bak"));
+        assert!(!wildcard_match("img_*", "my_img_1"));
+    }
+}
@@ -0,0 +1,982 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::{self, DirEntry, Metadata, ReadDir};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::error::{Operation, OrganizerError};
+use super::filter::FileFilter;
+use super::progress::Progress;
+use super::ProgressCallback;
+
+/// Archivo con patrones (sintaxis de `.gitignore`) de lo que no se debe organizar
+/// en su carpeta y en las que cuelgan de ella
+pub const IGNORE_FILE_NAME: &str = ".organizerignore";
+
+/// Qué hacer con los enlaces simbólicos que aparecen en el recorrido
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SymlinkPolicy {
+    /// Dejarlos donde están, anotados entre los archivos que no se mueven
+    #[default]
+    Skip,
+    /// Mover o copiar el propio enlace, sin entrar en él aunque sea una carpeta
+    Link,
+    /// Recorrer las carpetas enlazadas, sin pasar dos veces por la misma
+    /// (mismo dispositivo e inodo); los enlaces a archivos se trasladan como enlaces
+    Follow,
+}
+
+impl SymlinkPolicy {
+    pub const ALL: [SymlinkPolicy; 3] = [SymlinkPolicy::Skip, SymlinkPolicy::Link, SymlinkPolicy::Follow];
+
+    /// Nombre usado en la CLI (`--symlinks`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymlinkPolicy::Skip => "skip",
+            SymlinkPolicy::Link => "link",
+            SymlinkPolicy::Follow => "follow",
+        }
+    }
+
+    /// Texto mostrado en la interfaz gráfica
+    pub fn label(&self) -> &'static str {
+        match self {
+            SymlinkPolicy::Skip => "Dejarlos en su sitio",
+            SymlinkPolicy::Link => "Mover el enlace",
+            SymlinkPolicy::Follow => "Seguirlos",
+        }
+    }
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SymlinkPolicy::ALL
+            .into_iter()
+            .find(|policy| policy.name() == s)
+            .ok_or_else(|| format!("Política de enlaces desconocida '{}'", s))
+    }
+}
+
+/// Qué parte del árbol se recorre al buscar archivos
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// Niveles de subcarpetas que se recorren: `Some(0)` = solo la carpeta
+    /// seleccionada, `Some(n)` = hasta `n` niveles, `None` = sin límite
+    pub max_depth: Option<usize>,
+    /// Lo que no se toca, con la sintaxis de `.gitignore` sobre la ruta relativa
+    /// a la carpeta seleccionada (`*.part`, `node_modules/`, `/docs/keep.pdf`,
+    /// `!importante.part`) o una expresión regular con el prefijo `re:`
+    pub exclude: Vec<String>,
+    /// Si no está vacío, solo se organizan los archivos que coinciden con alguno
+    pub include: Vec<String>,
+    /// Respetar los archivos `.organizerignore` que haya en el árbol
+    pub ignore_files: bool,
+    /// Qué hacer con los enlaces simbólicos
+    pub symlinks: SymlinkPolicy,
+    /// Tamaño, fecha, nombre y archivos ocultos
+    pub filter: FileFilter,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            max_depth: Some(0),
+            exclude: Vec::new(),
+            include: Vec::new(),
+            ignore_files: true,
+            symlinks: SymlinkPolicy::default(),
+            filter: FileFilter::default(),
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Recorre todas las subcarpetas
+    pub fn recursive() -> Self {
+        ScanOptions { max_depth: None, ..Default::default() }
+    }
+
+    /// Indica si se entra en una subcarpeta que está a `depth` niveles de la raíz
+    /// (las subcarpetas directas están a nivel 1)
+    pub fn allows_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth <= max)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+/// Patrón de inclusión o exclusión ya compilado
+#[derive(Debug, Clone)]
+pub(crate) struct PathPattern {
+    matcher: Matcher,
+    /// `!patron`: vuelve a incluir lo que excluyó un patrón anterior
+    negated: bool,
+    /// `patron/`: solo coincide con carpetas
+    dir_only: bool,
+}
+
+impl PathPattern {
+    /// Interpreta un patrón al estilo de `.gitignore`. Sin `/` coincide con el
+    /// nombre a cualquier profundidad; con `/` se ancla a la carpeta raíz.
+    pub(crate) fn parse(pattern: &str) -> Result<Self, String> {
+        let invalid = |e: &dyn std::fmt::Display| format!("Patrón no válido '{}': {}", pattern, e);
+
+        let (negated, rest) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+
+        if let Some(regex) = rest.strip_prefix("re:") {
+            let regex = Regex::new(regex).map_err(|e| invalid(&e))?;
+            return Ok(PathPattern { matcher: Matcher::Regex(regex), negated, dir_only: false });
+        }
+
+        let (dir_only, rest) = match rest.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        if rest.is_empty() {
+            return Err(invalid(&"está vacío"));
+        }
+
+        let glob = match rest.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if rest.contains('/') => rest.to_string(),
+            None => format!("**/{}", rest),
+        };
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .case_insensitive(true)
+            .backslash_escape(true)
+            .build()
+            .map_err(|e| invalid(&e))?
+            .compile_matcher();
+
+        Ok(PathPattern { matcher: Matcher::Glob(matcher), negated, dir_only })
+    }
+
+    /// `relative` usa `/` como separador en todos los sistemas
+    fn is_match(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        match &self.matcher {
+            Matcher::Glob(glob) => glob.is_match(relative),
+            Matcher::Regex(regex) => regex.is_match(relative),
+        }
+    }
+}
+
+/// Lista de patrones en la que, como en `.gitignore`, manda el último que coincide
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PatternList {
+    patterns: Vec<PathPattern>,
+}
+
+impl PatternList {
+    pub(crate) fn parse<S: AsRef<str>>(patterns: &[S]) -> Result<Self, String> {
+        let patterns = patterns
+            .iter()
+            .map(|p| p.as_ref().trim())
+            .filter(|p| !p.is_empty())
+            .map(PathPattern::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(PatternList { patterns })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// `Some(true)` si el último patrón que coincide excluye, `Some(false)` si
+    /// es una negación y `None` si no coincide ninguno
+    pub(crate) fn matched(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.is_match(relative, is_dir))
+            .map(|pattern| !pattern.negated)
+    }
+
+    /// Como `matched` para un archivo, teniendo en cuenta también las carpetas
+    /// que lo contienen: `docs/` coincide con todo lo que cuelga de `docs`. Decide
+    /// la ruta más concreta que coincide con algún patrón.
+    pub(crate) fn matched_file_or_parent(&self, relative: &str) -> Option<bool> {
+        self.matched(relative, false).or_else(|| {
+            relative
+                .rmatch_indices('/')
+                .find_map(|(end, _)| self.matched(&relative[..end], true))
+        })
+    }
+}
+
+/// Archivo o carpeta que se dejó fuera por un `.organizerignore`
+#[derive(Debug, Clone, Serialize)]
+pub struct IgnoredPath {
+    pub path: PathBuf,
+    /// `.organizerignore` cuyo patrón lo excluyó
+    pub ignore_file: PathBuf,
+}
+
+/// Cuántos elementos excluyó cada `.organizerignore`
+pub fn count_by_ignore_file(ignored: &[IgnoredPath]) -> BTreeMap<&Path, usize> {
+    let mut counts = BTreeMap::new();
+    for item in ignored {
+        *counts.entry(item.ignore_file.as_path()).or_default() += 1;
+    }
+    counts
+}
+
+/// Patrones de un `.organizerignore`, relativos a la carpeta que lo contiene
+struct IgnoreFile {
+    path: PathBuf,
+    /// Carpeta del archivo, relativa a la raíz (`""` en la raíz)
+    base: String,
+    patterns: PatternList,
+}
+
+impl IgnoreFile {
+    /// Lee el `.organizerignore` de una carpeta, si lo hay
+    fn load(dir_path: &Path, root: &Path) -> Result<Option<Self>, OrganizerError> {
+        let path = dir_path.join(IGNORE_FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| OrganizerError::io(Operation::ReadFile, &path, e))?;
+        let lines: Vec<&str> = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .collect();
+        let patterns = PatternList::parse(&lines)
+            .map_err(|e| OrganizerError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+
+        Ok(Some(IgnoreFile { base: relative_string(dir_path, root), path, patterns }))
+    }
+
+    /// Decisión de este archivo sobre una ruta relativa a la raíz, si le afecta
+    fn matched(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        let scoped = if self.base.is_empty() {
+            relative
+        } else {
+            relative.strip_prefix(&self.base)?.strip_prefix('/')?
+        };
+        self.patterns.matched(scoped, is_dir)
+    }
+}
+
+/// Archivo encontrado en el recorrido, con los metadatos ya leídos
+#[derive(Debug)]
+pub(crate) struct ScannedFile {
+    pub(crate) path: PathBuf,
+    pub(crate) metadata: Metadata,
+}
+
+/// Carpeta que no se pudo recorrer (lo que haya dentro se queda sin organizar) o
+/// entrada de la que no se pudieron leer los metadatos
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreadablePath {
+    pub path: PathBuf,
+    /// Tipo del error, como en `OrganizerError::kind` (`permission_denied`, `io`...)
+    pub kind: &'static str,
+    pub error: String,
+}
+
+impl UnreadablePath {
+    pub(crate) fn new(path: PathBuf, error: &OrganizerError) -> Self {
+        UnreadablePath { path, kind: error.kind(), error: error.to_string() }
+    }
+}
+
+/// Cada cosa que va encontrando el recorrido, en el orden del árbol
+#[derive(Debug)]
+pub(crate) enum ScanItem {
+    File(ScannedFile),
+    Ignored(IgnoredPath),
+    /// No pasó el filtro de tamaño, fecha, nombre u ocultos
+    Filtered(PathBuf),
+    /// Algo que no se puede organizar: un enlace que no se sigue, un socket,
+    /// una FIFO, un dispositivo o una carpeta por la que ya se pasó
+    Skipped { path: PathBuf, reason: String },
+    /// Una carpeta o entrada que no se pudo leer; el recorrido sigue con las demás
+    Unreadable { path: PathBuf, error: OrganizerError },
+}
+
+/// Lo que encontró un recorrido completo
+#[derive(Debug, Default)]
+pub(crate) struct ScanOutput {
+    pub(crate) files: Vec<ScannedFile>,
+    pub(crate) ignored: Vec<IgnoredPath>,
+    /// Lo que no pasó el filtro de tamaño, fecha, nombre u ocultos
+    pub(crate) filtered: Vec<PathBuf>,
+    pub(crate) errors: Vec<OrganizerError>,
+}
+
+/// Ruta relativa con `/` como separador, que es lo que esperan los patrones
+pub(crate) fn relative_string(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Recorre la carpeta seleccionada aplicando las opciones de escaneo
+pub(crate) struct Scanner<'a> {
+    root: &'a Path,
+    options: &'a ScanOptions,
+    /// Carpeta de destino ya canonicalizada; nunca se recorre
+    output: Option<&'a Path>,
+    exclude: PatternList,
+    include: PatternList,
+}
+
+impl<'a> Scanner<'a> {
+    pub(crate) fn new(root: &'a Path, options: &'a ScanOptions, output: Option<&'a Path>) -> Result<Self, OrganizerError> {
+        options.filter.validate().map_err(OrganizerError::InvalidConfig)?;
+        Ok(Scanner {
+            root,
+            options,
+            output,
+            exclude: PatternList::parse(&options.exclude).map_err(OrganizerError::InvalidConfig)?,
+            include: PatternList::parse(&options.include).map_err(OrganizerError::InvalidConfig)?,
+        })
+    }
+
+    /// Recorre el árbol entero y reúne lo encontrado, avisando a
+    /// `progress_callback` de cada archivo
+    pub(crate) fn collect(self, progress_callback: &ProgressCallback<'_>) -> ScanOutput {
+        let mut output = ScanOutput::default();
+        let started = Instant::now();
+        let mut walk = self.walk();
+        while let Some(item) = walk.next() {
+            match item {
+                ScanItem::File(file) => {
+                    progress_callback(&walk.progress(&file.path, started.elapsed()));
+                    output.files.push(file);
+                }
+                ScanItem::Ignored(ignored) => output.ignored.push(ignored),
+                ScanItem::Filtered(path) => output.filtered.push(path),
+                // Lo que no es un archivo normal no interesa a quien quiere la lista completa
+                ScanItem::Skipped { .. } => {}
+                ScanItem::Unreadable { error, .. } => output.errors.push(error),
+            }
+        }
+        output
+    }
+
+    /// Recorrido perezoso: cada carpeta se lee cuando se llega a ella
+    pub(crate) fn walk(self) -> Walk<'a> {
+        Walk {
+            scanner: self,
+            started: false,
+            stack: Vec::new(),
+            ignores: Vec::new(),
+            visited: HashSet::new(),
+            ready: VecDeque::new(),
+            files_found: 0,
+            bytes_found: 0,
+            dirs_read: 0,
+            pending_entries: 0,
+            pending_dirs: 0,
+        }
+    }
+}
+
+/// Entradas que se leen por adelantado de cada carpeta abierta: bastan para
+/// estimar el total sin tener en memoria una carpeta entera
+const READ_AHEAD: usize = 64;
+
+/// Carpeta abierta durante el recorrido, con las entradas que quedan por ver
+struct DirFrame {
+    path: PathBuf,
+    /// Lo que queda por leer de la carpeta; `None` cuando se acabó o falló la lectura
+    reader: Option<ReadDir>,
+    /// Entradas ya leídas que aún no se han visto, cada una con si es una carpeta
+    ahead: VecDeque<(DirEntry, bool)>,
+    /// Nivel de las subcarpetas de esta carpeta
+    depth: usize,
+    /// Si esta carpeta añadió su `.organizerignore` a la pila
+    has_ignore: bool,
+}
+
+/// Iterador del recorrido. Sigue el mismo orden que un recorrido en
+/// profundidad: una subcarpeta se recorre en cuanto aparece.
+pub(crate) struct Walk<'a> {
+    scanner: Scanner<'a>,
+    started: bool,
+    stack: Vec<DirFrame>,
+    /// `.organizerignore` de las carpetas abiertas, el más externo primero
+    ignores: Vec<IgnoreFile>,
+    /// Carpetas ya abiertas, para no recorrer dos veces la misma por un enlace
+    visited: HashSet<DirId>,
+    /// Elementos ya encontrados que aún no se han devuelto
+    ready: VecDeque<ScanItem>,
+    files_found: usize,
+    bytes_found: u64,
+    dirs_read: usize,
+    /// Entradas ya leídas que no son carpetas y aún no se han visto
+    pending_entries: usize,
+    /// Subcarpetas ya leídas que aún no se han abierto
+    pending_dirs: usize,
+}
+
+impl Walk<'_> {
+    /// Indica si el recorrido ya terminó, y con él las estimaciones
+    pub(crate) fn is_finished(&self) -> bool {
+        self.started && self.stack.is_empty() && self.ready.is_empty()
+    }
+
+    /// Archivos que se espera encontrar en total: los encontrados, las entradas
+    /// que quedan en las carpetas abiertas y, por cada subcarpeta aún sin abrir,
+    /// la media de archivos por carpeta hasta ahora
+    pub(crate) fn estimated_files(&self) -> usize {
+        if self.is_finished() {
+            return self.files_found;
+        }
+        let per_dir = self.files_found as f64 / self.dirs_read.max(1) as f64;
+        self.files_found + self.pending_entries + (self.pending_dirs as f64 * per_dir).round() as usize
+    }
+
+    /// Avance del recorrido con `file` como último archivo encontrado; los
+    /// totales son estimaciones hasta que termina
+    pub(crate) fn progress(&self, file: &Path, elapsed: Duration) -> Progress {
+        Progress {
+            current: self.files_found,
+            total: self.estimated_files(),
+            file: file.to_path_buf(),
+            bytes_done: self.bytes_found,
+            bytes_total: self.estimated_bytes(),
+            elapsed,
+            estimated: !self.is_finished(),
+        }
+    }
+
+    /// Bytes que se espera encontrar, con el tamaño medio de los archivos encontrados
+    pub(crate) fn estimated_bytes(&self) -> u64 {
+        if self.files_found == 0 {
+            return 0;
+        }
+        let per_file = self.bytes_found as f64 / self.files_found as f64;
+        self.bytes_found.max((per_file * self.estimated_files() as f64).round() as u64)
+    }
+
+    /// Abre una carpeta y la pone en la cima de la pila; sus entradas se leen
+    /// según se van necesitando. Si no se puede leer, el error queda como un
+    /// elemento más del recorrido.
+    fn open(&mut self, dir_path: &Path, depth: usize) {
+        // Un enlace que lleva a una carpeta ya recorrida (o a una de sus madres) cerraría un ciclo
+        if let Ok(metadata) = fs::metadata(dir_path)
+            && !self.visited.insert(DirId::of(dir_path, &metadata))
+        {
+            self.ready.push_back(ScanItem::Skipped {
+                path: dir_path.to_path_buf(),
+                reason: "carpeta ya recorrida por otra ruta (enlace simbólico)".to_string(),
+            });
+            return;
+        }
+
+        let entries = match fs::read_dir(dir_path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.unreadable(dir_path, OrganizerError::io(Operation::ReadDir, dir_path, e));
+                return;
+            }
+        };
+
+        // Sin su `.organizerignore` no se sabe qué se puede tocar, así que la carpeta se salta
+        let own_ignore = if self.scanner.options.ignore_files {
+            match IgnoreFile::load(dir_path, self.scanner.root) {
+                Ok(ignore) => ignore,
+                Err(e) => {
+                    self.unreadable(dir_path, e);
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        self.dirs_read += 1;
+        let has_ignore = own_ignore.is_some();
+        self.ignores.extend(own_ignore);
+        self.stack.push(DirFrame {
+            path: dir_path.to_path_buf(),
+            reader: Some(entries),
+            ahead: VecDeque::new(),
+            depth,
+            has_ignore,
+        });
+    }
+
+    /// Lee las siguientes entradas de la carpeta en la cima de la pila
+    fn read_ahead(&mut self) {
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        let walks_subdirs = self.scanner.options.allows_depth(frame.depth);
+        let mut failed = None;
+        while frame.ahead.len() < READ_AHEAD {
+            let Some(reader) = frame.reader.as_mut() else {
+                break;
+            };
+            match reader.next() {
+                Some(Ok(entry)) => {
+                    let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+                    if is_dir && walks_subdirs {
+                        self.pending_dirs += 1;
+                    } else if !is_dir {
+                        self.pending_entries += 1;
+                    }
+                    frame.ahead.push_back((entry, is_dir));
+                }
+                Some(Err(e)) => {
+                    frame.reader = None;
+                    failed = Some(OrganizerError::io(Operation::ReadDir, &frame.path, e));
+                }
+                None => frame.reader = None,
+            }
+        }
+
+        // Lo que se leyó hasta el error se organiza igualmente
+        if let Some(error) = failed {
+            let path = frame.path.clone();
+            self.unreadable(&path, error);
+        }
+    }
+
+    fn unreadable(&mut self, dir_path: &Path, error: OrganizerError) {
+        self.ready.push_back(ScanItem::Unreadable { path: dir_path.to_path_buf(), error });
+    }
+
+    /// Decide qué hacer con una entrada de la carpeta en curso, saltándose lo
+    /// excluido y la carpeta de destino. Las subcarpetas se abren si las
+    /// opciones lo permiten y no devuelven nada.
+    fn visit(&mut self, entry: DirEntry, depth: usize) -> Option<ScanItem> {
+        let scanner = &self.scanner;
+        let path = entry.path();
+
+        // Los propios `.organizerignore` se quedan siempre en su sitio
+        if entry.file_name() == IGNORE_FILE_NAME {
+            return None;
+        }
+
+        // Un enlace solo se trata como lo que apunta si se siguen; roto, sigue siendo un enlace
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                let error = OrganizerError::io(Operation::ReadMetadata, &path, e);
+                return Some(ScanItem::Unreadable { path, error });
+            }
+        };
+        let metadata = if metadata.is_symlink() && scanner.options.symlinks == SymlinkPolicy::Follow {
+            fs::metadata(&path).unwrap_or(metadata)
+        } else {
+            metadata
+        };
+        let is_dir = metadata.is_dir();
+        let relative = relative_string(&path, scanner.root);
+
+        // Una carpeta excluida no se recorre
+        if scanner.exclude.matched(&relative, is_dir) == Some(true) {
+            return None;
+        }
+
+        // El `.organizerignore` más profundo que opina sobre la ruta es el que decide
+        let decision = self
+            .ignores
+            .iter()
+            .rev()
+            .find_map(|ignore| ignore.matched(&relative, is_dir).map(|excluded| (ignore, excluded)));
+        if let Some((ignore, true)) = decision {
+            return Some(ScanItem::Ignored(IgnoredPath { path, ignore_file: ignore.path.clone() }));
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if is_dir {
+            if !scanner.options.allows_depth(depth) {
+                return None;
+            }
+            // Una carpeta oculta no se recorre si no se piden los ocultos; no es un archivo descartado
+            if scanner.options.filter.hides(&name, &metadata) {
+                return None;
+            }
+            // Skip the destination folder, wherever it is in the tree
+            if scanner.output.is_some_and(|output| fs::canonicalize(&path).is_ok_and(|p| p == output)) {
+                return None;
+            }
+            self.open(&path, depth + 1);
+            return None;
+        }
+
+        if scanner.options.filter.hides(&name, &metadata) {
+            return Some(ScanItem::Filtered(path));
+        }
+        if !scanner.include.is_empty() && scanner.include.matched_file_or_parent(&relative) != Some(true) {
+            return None;
+        }
+        if let Some(reason) = unsupported_reason(&metadata, scanner.options.symlinks) {
+            return Some(ScanItem::Skipped { path, reason: reason.to_string() });
+        }
+        if !scanner.options.filter.matches(&name, &metadata) {
+            return Some(ScanItem::Filtered(path));
+        }
+        self.files_found += 1;
+        self.bytes_found += metadata.len();
+        Some(ScanItem::File(ScannedFile { path, metadata }))
+    }
+}
+
+/// Por qué algo que no es una carpeta no se puede organizar, si es el caso
+fn unsupported_reason(metadata: &Metadata, symlinks: SymlinkPolicy) -> Option<&'static str> {
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        return (symlinks == SymlinkPolicy::Skip).then_some("es un enlace simbólico");
+    }
+    if file_type.is_file() {
+        return None;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return Some("es una tubería con nombre (FIFO)");
+        }
+        if file_type.is_socket() {
+            return Some("es un socket");
+        }
+        if file_type.is_block_device() || file_type.is_char_device() {
+            return Some("es un dispositivo");
+        }
+    }
+    Some("no es un archivo normal")
+}
+
+/// Identidad de una carpeta en el disco, la misma aunque se llegue por enlaces distintos
+#[cfg(unix)]
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct DirId(u64, u64);
+
+#[cfg(not(unix))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct DirId(PathBuf);
+
+impl DirId {
+    #[cfg(unix)]
+    fn of(_path: &Path, metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        DirId(metadata.dev(), metadata.ino())
+    }
+
+    /// Sin dispositivo e inodo estables, la ruta canónica hace las veces de identidad
+    #[cfg(not(unix))]
+    fn of(path: &Path, _metadata: &Metadata) -> Self {
+        DirId(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = ScanItem;
+
+    fn next(&mut self) -> Option<ScanItem> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some(item);
+            }
+            if !self.started {
+                self.started = true;
+                let root = self.scanner.root;
+                self.open(root, 1);
+                continue;
+            }
+
+            let frame = self.stack.last_mut()?;
+            if frame.ahead.is_empty() && frame.reader.is_some() {
+                self.read_ahead();
+                continue;
+            }
+            let depth = frame.depth;
+            let Some((entry, is_dir)) = frame.ahead.pop_front() else {
+                if frame.has_ignore {
+                    self.ignores.pop();
+                }
+                self.stack.pop();
+                continue;
+            };
+
+            if !is_dir {
+                self.pending_entries -= 1;
+            } else if self.scanner.options.allows_depth(depth) {
+                self.pending_dirs -= 1;
+            }
+            if let Some(item) = self.visit(entry, depth) {
+                return Some(item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::testing::TempDir;
+
+    fn list(patterns: &[&str]) -> PatternList {
+        PatternList::parse(patterns).unwrap()
+    }
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let patterns = list(&["*.log", "!keep.log", "keep.log"]);
+        assert_eq!(patterns.matched("keep.log", false), Some(true));
+
+        let patterns = list(&["*.log", "!keep.log"]);
+        assert_eq!(patterns.matched("logs/keep.log", false), Some(false));
+        assert_eq!(patterns.matched("logs/other.log", false), Some(true));
+        assert_eq!(patterns.matched("notes.txt", false), None);
+    }
+
+    #[test]
+    fn trailing_slash_only_matches_folders() {
+        let patterns = list(&["build/"]);
+        assert_eq!(patterns.matched("build", true), Some(true));
+        assert_eq!(patterns.matched("src/build", true), Some(true));
+        assert_eq!(patterns.matched("build", false), None);
+    }
+
+    #[test]
+    fn slash_anchors_to_the_root() {
+        let patterns = list(&["/tmp"]);
+        assert_eq!(patterns.matched("tmp", true), Some(true));
+        assert_eq!(patterns.matched("src/tmp", true), None);
+
+        let patterns = list(&["docs/*.md"]);
+        assert_eq!(patterns.matched("docs/a.md", false), Some(true));
+        assert_eq!(patterns.matched("src/docs/a.md", false), None);
+        // `*` no cruza carpetas
+        assert_eq!(patterns.matched("docs/sub/a.md", false), None);
+
+        let patterns = list(&["*.tmp"]);
+        assert_eq!(patterns.matched("a/b/c.TMP", false), Some(true));
+    }
+
+    #[test]
+    fn regex_and_invalid_patterns() {
+        let patterns = list(&[r"re:^photos/\d{4}/", "!re:keep"]);
+        assert_eq!(patterns.matched("photos/2024/a.jpg", false), Some(true));
+        assert_eq!(patterns.matched("photos/2024/keep.jpg", false), Some(false));
+        assert_eq!(patterns.matched("old/photos/2024/a.jpg", false), None);
+
+        assert!(list(&["", "  "]).is_empty());
+        assert!(PatternList::parse(&["re:("]).is_err());
+        assert!(PatternList::parse(&["!/"]).is_err());
+    }
+
+    #[test]
+    fn folder_patterns_include_what_is_inside() {
+        let patterns = list(&["docs/", "!docs/private/"]);
+        assert_eq!(patterns.matched_file_or_parent("docs/a.md"), Some(true));
+        assert_eq!(patterns.matched_file_or_parent("docs/sub/b.md"), Some(true));
+        assert_eq!(patterns.matched_file_or_parent("docs/private/c.md"), Some(false));
+        assert_eq!(patterns.matched_file_or_parent("src/a.md"), None);
+        // Un archivo que se llama como la carpeta no es la carpeta
+        assert_eq!(patterns.matched_file_or_parent("docs"), None);
+    }
+
+    fn walk(root: &Path, options: &ScanOptions) -> Vec<ScanItem> {
+        Scanner::new(root, options, None).unwrap().walk().collect()
+    }
+
+    /// Rutas relativas de los archivos encontrados, ordenadas
+    fn files(root: &Path, items: &[ScanItem]) -> Vec<String> {
+        let mut files: Vec<String> = items
+            .iter()
+            .filter_map(|item| match item {
+                ScanItem::File(file) => Some(relative_string(&file.path, root)),
+                _ => None,
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn include_folder_pattern_keeps_its_files() {
+        let dir = TempDir::new("scan-include-folder");
+        dir.write("docs/a.md", "a");
+        dir.write("docs/sub/b.md", "b");
+        dir.write("src/c.md", "c");
+        let options = ScanOptions { include: vec!["docs/".to_string()], ..ScanOptions::recursive() };
+        assert_eq!(files(dir.path(), &walk(dir.path(), &options)), ["docs/a.md", "docs/sub/b.md"]);
+    }
+
+    #[test]
+    fn hidden_folders_are_pruned_not_filtered() {
+        let dir = TempDir::new("scan-hidden");
+        dir.write(".hidden.txt", "x");
+        dir.write(".git/config", "x");
+        dir.write("visible/a.txt", "x");
+        let filter = FileFilter { include_hidden: false, ..Default::default() };
+
+        for max_depth in [Some(0), None] {
+            let options = ScanOptions { max_depth, filter: filter.clone(), ..Default::default() };
+            let items = walk(dir.path(), &options);
+            let filtered: Vec<&PathBuf> = items
+                .iter()
+                .filter_map(|item| match item {
+                    ScanItem::Filtered(path) => Some(path),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(filtered, [&dir.join(".hidden.txt")]);
+        }
+
+        let items = walk(dir.path(), &ScanOptions { filter, ..ScanOptions::recursive() });
+        assert_eq!(files(dir.path(), &items), ["visible/a.txt"]);
+    }
+
+    #[test]
+    fn entries_come_out_before_the_folder_is_read_whole() {
+        let dir = TempDir::new("scan-lazy");
+        let total = READ_AHEAD * 3;
+        for i in 0..total {
+            dir.write(format!("{}.txt", i), "x");
+        }
+
+        let options = ScanOptions::default();
+        let mut walk = Scanner::new(dir.path(), &options, None).unwrap().walk();
+        assert!(matches!(walk.next(), Some(ScanItem::File(_))));
+        // Solo se ha leído lo necesario para el primer archivo y el resto de su tanda
+        assert_eq!(walk.files_found, 1);
+        assert!(walk.files_found + walk.pending_entries <= READ_AHEAD);
+        assert!(!walk.is_finished());
+
+        assert_eq!(walk.count() + 1, total);
+    }
+
+    #[test]
+    fn unreadable_folder_is_reported_and_the_walk_goes_on() {
+        let dir = TempDir::new("scan-unreadable");
+        dir.write("a.txt", "x");
+        // Un `.organizerignore` que no se entiende deja la carpeta sin recorrer
+        dir.write(Path::new("bad").join(IGNORE_FILE_NAME), "re:(\n");
+        dir.write("bad/b.txt", "x");
+        dir.write("good/c.txt", "x");
+
+        let items = walk(dir.path(), &ScanOptions::recursive());
+        assert_eq!(files(dir.path(), &items), ["a.txt", "good/c.txt"]);
+        let unreadable: Vec<&PathBuf> = items
+            .iter()
+            .filter_map(|item| match item {
+                ScanItem::Unreadable { path, .. } => Some(path),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(unreadable, [&dir.join("bad")]);
+    }
+
+    #[test]
+    fn entry_that_vanishes_is_reported_not_dropped() {
+        let dir = TempDir::new("scan-vanished");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            dir.write(name, "x");
+        }
+
+        let options = ScanOptions::default();
+        let mut walk = Scanner::new(dir.path(), &options, None).unwrap().walk();
+        let Some(ScanItem::File(first)) = walk.next() else {
+            panic!("se esperaba un archivo");
+        };
+        // Las otras dos ya están leídas de la carpeta pero aún no se han visto
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            if dir.join(name) != first.path {
+                fs::remove_file(dir.join(name)).unwrap();
+            }
+        }
+
+        let rest: Vec<ScanItem> = walk.collect();
+        assert_eq!(rest.len(), 2);
+        for item in rest {
+            let ScanItem::Unreadable { error, .. } = item else {
+                panic!("se esperaba una entrada ilegible: {:?}", item);
+            };
+            assert_eq!(error.operation(), Some(Operation::ReadMetadata));
+        }
+    }
+
+    #[test]
+    fn depth_limits_the_folders_walked() {
+        let dir = TempDir::new("scan-depth");
+        for file in ["a.txt", "one/b.txt", "one/two/c.txt", "one/two/three/d.txt"] {
+            dir.write(file, "x");
+        }
+        let found = |max_depth| files(dir.path(), &walk(dir.path(), &ScanOptions { max_depth, ..Default::default() }));
+
+        assert_eq!(found(Some(0)), ["a.txt"]);
+        assert_eq!(found(Some(2)), ["a.txt", "one/b.txt", "one/two/c.txt"]);
+        assert_eq!(found(None), ["a.txt", "one/b.txt", "one/two/c.txt", "one/two/three/d.txt"]);
+        assert_eq!(ScanOptions::default().max_depth, Some(0));
+    }
+
+    #[test]
+    fn nested_ignore_files_override_outer_ones() {
+        let dir = TempDir::new("scan-ignore");
+        dir.write(IGNORE_FILE_NAME, "*.log\nbuild/\n");
+        dir.write("a.log", "x");
+        dir.write("a.txt", "x");
+        dir.write("build/out.txt", "x");
+        dir.write(Path::new("sub").join(IGNORE_FILE_NAME), "# el de dentro manda\n!keep.log\n");
+        dir.write("sub/keep.log", "x");
+        dir.write("sub/other.log", "x");
+        // Un patrón anclado de la subcarpeta no afecta a la raíz
+        dir.write(Path::new("other").join(IGNORE_FILE_NAME), "/a.txt\n");
+        dir.write("other/a.txt", "x");
+
+        let items = walk(dir.path(), &ScanOptions::recursive());
+        assert_eq!(files(dir.path(), &items), ["a.txt", "sub/keep.log"]);
+
+        let mut ignored: Vec<(String, String)> = items
+            .iter()
+            .filter_map(|item| match item {
+                ScanItem::Ignored(ignored) => Some((
+                    relative_string(&ignored.path, dir.path()),
+                    relative_string(&ignored.ignore_file, dir.path()),
+                )),
+                _ => None,
+            })
+            .collect();
+        ignored.sort();
+        let expected = [
+            ("a.log", ".organizerignore"),
+            ("build", ".organizerignore"),
+            ("other/a.txt", "other/.organizerignore"),
+            ("sub/other.log", ".organizerignore"),
+        ];
+        assert_eq!(ignored, expected.map(|(path, file)| (path.to_string(), file.to_string())));
+
+        let without = ScanOptions { ignore_files: false, ..ScanOptions::recursive() };
+        assert_eq!(files(dir.path(), &walk(dir.path(), &without)).len(), 6);
+    }
+}
@@ -0,0 +1,277 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Bytes leídos del principio de cada archivo para reconocer su tipo
+const HEADER_SIZE: usize = 8192;
+
+/// Cómo usar el tipo detectado por contenido al clasificar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TypeDetection {
+    /// Solo la extensión, sin leer los archivos
+    #[default]
+    Off,
+    /// La extensión manda; el contenido solo se usa si no hay extensión
+    ExtensionFirst,
+    /// El contenido manda cuando no coincide con la extensión
+    ContentFirst,
+}
+
+impl TypeDetection {
+    pub const ALL: [TypeDetection; 3] = [
+        TypeDetection::Off,
+        TypeDetection::ExtensionFirst,
+        TypeDetection::ContentFirst,
+    ];
+
+    /// Texto mostrado en la interfaz gráfica
+    pub fn label(&self) -> &'static str {
+        match self {
+            TypeDetection::Off => "Solo extension",
+            TypeDetection::ExtensionFirst => "Extension, o contenido si no hay",
+            TypeDetection::ContentFirst => "Contenido antes que extension",
+        }
+    }
+}
+
+/// Tipo de archivo reconocido por su firma
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DetectedType {
+    /// Extensión habitual, sin punto
+    pub extension: &'static str,
+    pub mime_type: &'static str,
+    /// Otras extensiones válidas para este contenido
+    #[serde(skip)]
+    pub aliases: &'static [&'static str],
+}
+
+impl DetectedType {
+    const fn new(extension: &'static str, mime_type: &'static str, aliases: &'static [&'static str]) -> Self {
+        DetectedType { extension, mime_type, aliases }
+    }
+
+    /// Indica si `extension` es una extensión razonable para este contenido
+    pub fn accepts_extension(&self, extension: &str) -> bool {
+        self.extension.eq_ignore_ascii_case(extension)
+            || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(extension))
+    }
+}
+
+/// Archivo cuya extensión no corresponde con su contenido
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeMismatch {
+    pub path: PathBuf,
+    pub extension: String,
+    pub detected: DetectedType,
+}
+
+/// Lee la cabecera de un archivo e intenta reconocer su tipo
+pub fn detect_file_type(path: &Path) -> io::Result<Option<DetectedType>> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    File::open(path)?
+        .take(HEADER_SIZE as u64)
+        .read_to_end(&mut header)?;
+    Ok(detect_from_bytes(&header))
+}
+
+/// Reconoce el tipo a partir de los primeros bytes del contenido
+pub fn detect_from_bytes(header: &[u8]) -> Option<DetectedType> {
+    let starts = |magic: &[u8]| header.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+    let detected = if starts(b"\x89PNG\r\n\x1a\n") {
+        DetectedType::new("png", "image/png", &[])
+    } else if starts(b"\xff\xd8\xff") {
+        DetectedType::new("jpg", "image/jpeg", &["jpeg", "jpe", "jfif"])
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        DetectedType::new("gif", "image/gif", &[])
+    } else if is_bmp(header) {
+        DetectedType::new("bmp", "image/bmp", &["dib"])
+    } else if starts(b"II*\0") || starts(b"MM\0*") {
+        DetectedType::new("tif", "image/tiff", &["tiff", "dng", "nef", "cr2", "arw"])
+    } else if starts(b"RIFF") && at(8, b"WEBP") {
+        DetectedType::new("webp", "image/webp", &[])
+    } else if starts(b"RIFF") && at(8, b"WAVE") {
+        DetectedType::new("wav", "audio/wav", &[])
+    } else if starts(b"RIFF") && at(8, b"AVI ") {
+        DetectedType::new("avi", "video/x-msvideo", &[])
+    } else if starts(b"%PDF-") {
+        DetectedType::new("pdf", "application/pdf", &["ai"])
+    } else if starts(b"PK\x03\x04") {
+        detect_zip(header)
+    } else if starts(b"\x7fELF") {
+        DetectedType::new("elf", "application/x-elf", &["so", "o", "bin", "out"])
+    } else if is_pe(header) {
+        DetectedType::new("exe", "application/x-msdownload", &["dll", "msi", "sys", "scr", "com"])
+    } else if starts(b"ID3") || (header.len() >= 2 && header[0] == 0xff && matches!(header[1], 0xfb | 0xf3 | 0xf2)) {
+        DetectedType::new("mp3", "audio/mpeg", &[])
+    } else if at(4, b"ftyp") {
+        detect_iso_media(header)
+    } else if starts(b"\x1a\x45\xdf\xa3") {
+        if header.windows(4).any(|w| w == b"webm") {
+            DetectedType::new("webm", "video/webm", &[])
+        } else {
+            DetectedType::new("mkv", "video/x-matroska", &["mka", "mk3d"])
+        }
+    } else if starts(b"OggS") {
+        DetectedType::new("ogg", "audio/ogg", &["oga", "ogv", "opus"])
+    } else if starts(b"fLaC") {
+        DetectedType::new("flac", "audio/flac", &[])
+    } else if starts(b"\x1f\x8b") {
+        DetectedType::new("gz", "application/gzip", &["tgz", "gzip"])
+    } else if starts(b"BZh") {
+        DetectedType::new("bz2", "application/x-bzip2", &["tbz2", "tbz"])
+    } else if starts(b"\xfd7zXZ\0") {
+        DetectedType::new("xz", "application/x-xz", &["txz"])
+    } else if starts(b"\x28\xb5\x2f\xfd") {
+        DetectedType::new("zst", "application/zstd", &["tzst"])
+    } else if starts(b"7z\xbc\xaf\x27\x1c") {
+        DetectedType::new("7z", "application/x-7z-compressed", &[])
+    } else if starts(b"Rar!\x1a\x07") {
+        DetectedType::new("rar", "application/vnd.rar", &[])
+    } else if at(257, b"ustar") {
+        DetectedType::new("tar", "application/x-tar", &[])
+    } else if starts(b"SQLite format 3\0") {
+        DetectedType::new("sqlite", "application/vnd.sqlite3", &["db", "sqlite3"])
+    } else if starts(b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1") {
+        // Formato OLE de los documentos antiguos de Office
+        DetectedType::new("doc", "application/msword", &["xls", "ppt", "msg", "msi"])
+    } else {
+        return None;
+    };
+
+    Some(detected)
+}
+
+/// Lee un entero de 32 bits little-endian en `offset`
+fn read_u32_le(header: &[u8], offset: usize) -> Option<u32> {
+    let bytes = header.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// `BM` solo no basta (lo empieza cualquier texto): la cabecera DIB debe tener un
+/// tamaño conocido y el tamaño del archivo y el inicio de los píxeles, cuadrar con ella
+fn is_bmp(header: &[u8]) -> bool {
+    if !header.starts_with(b"BM") {
+        return false;
+    }
+    let (Some(file_size), Some(data_offset), Some(dib_size)) =
+        (read_u32_le(header, 2), read_u32_le(header, 10), read_u32_le(header, 14))
+    else {
+        return false;
+    };
+
+    matches!(dib_size, 12 | 40 | 108 | 124)
+        && data_offset >= 14 + dib_size
+        && file_size >= data_offset
+}
+
+/// Un ejecutable de Windows empieza por `MZ` y en `e_lfanew` (0x3C) apunta a la
+/// firma `PE\0\0`
+fn is_pe(header: &[u8]) -> bool {
+    if !header.starts_with(b"MZ") {
+        return false;
+    }
+    read_u32_le(header, 0x3c)
+        .and_then(|offset| usize::try_from(offset).ok())
+        .and_then(|offset| header.get(offset..offset.checked_add(4)?))
+        == Some(b"PE\0\0".as_slice())
+}
+
+/// Distingue los formatos basados en ZIP (OOXML, OpenDocument, EPUB...) por su contenido
+fn detect_zip(header: &[u8]) -> DetectedType {
+    let contains = |needle: &[u8]| header.windows(needle.len()).any(|w| w == needle);
+
+    if contains(b"mimetypeapplication/epub+zip") {
+        DetectedType::new("epub", "application/epub+zip", &[])
+    } else if contains(b"mimetypeapplication/vnd.oasis.opendocument.text") {
+        DetectedType::new("odt", "application/vnd.oasis.opendocument.text", &[])
+    } else if contains(b"mimetypeapplication/vnd.oasis.opendocument.spreadsheet") {
+        DetectedType::new("ods", "application/vnd.oasis.opendocument.spreadsheet", &[])
+    } else if contains(b"mimetypeapplication/vnd.oasis.opendocument.presentation") {
+        DetectedType::new("odp", "application/vnd.oasis.opendocument.presentation", &[])
+    } else if contains(b"word/") {
+        DetectedType::new("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document", &["docm", "dotx"])
+    } else if contains(b"xl/") {
+        DetectedType::new("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", &["xlsm", "xltx"])
+    } else if contains(b"ppt/") {
+        DetectedType::new("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation", &["pptm", "potx"])
+    } else if contains(b"AndroidManifest.xml") {
+        DetectedType::new("apk", "application/vnd.android.package-archive", &["zip"])
+    } else if contains(b"META-INF/MANIFEST.MF") {
+        DetectedType::new("jar", "application/java-archive", &["war", "ear", "zip"])
+    } else {
+        DetectedType::new("zip", "application/zip", &["jar", "apk", "xpi", "whl", "nupkg", "ipa", "cbz", "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub"])
+    }
+}
+
+/// Contenedores ISO (MP4, MOV, HEIC...) según la marca de `ftyp`
+fn detect_iso_media(header: &[u8]) -> DetectedType {
+    match header.get(8..12) {
+        Some(b"qt  ") => DetectedType::new("mov", "video/quicktime", &["qt"]),
+        Some(b"M4A ") | Some(b"M4B ") => DetectedType::new("m4a", "audio/mp4", &["m4b", "mp4"]),
+        Some(b"heic") | Some(b"heix") | Some(b"mif1") | Some(b"msf1") => {
+            DetectedType::new("heic", "image/heic", &["heif"])
+        }
+        Some(b"avif") => DetectedType::new("avif", "image/avif", &[]),
+        Some(b"3gp4") | Some(b"3gp5") | Some(b"3gp6") | Some(b"3g2a") => {
+            DetectedType::new("3gp", "video/3gpp", &["3g2", "mp4"])
+        }
+        _ => DetectedType::new("mp4", "video/mp4", &["m4v", "m4a", "mov", "3gp"]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bmp_header(file_size: u32, data_offset: u32, dib_size: u32) -> Vec<u8> {
+        let mut header = b"BM".to_vec();
+        header.extend_from_slice(&file_size.to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&data_offset.to_le_bytes());
+        header.extend_from_slice(&dib_size.to_le_bytes());
+        header.resize(64, 0);
+        header
+    }
+
+    fn extension(header: &[u8]) -> Option<&'static str> {
+        detect_from_bytes(header).map(|detected| detected.extension)
+    }
+
+    #[test]
+    fn bmp_needs_a_consistent_header() {
+        assert_eq!(extension(&bmp_header(1078 + 64, 1078, 40)), Some("bmp"));
+        assert_eq!(extension(&bmp_header(200, 138, 124)), Some("bmp"));
+        // Tamaño de cabecera DIB desconocido
+        assert_eq!(extension(&bmp_header(1000, 54, 41)), None);
+        // Los píxeles no pueden empezar dentro de las cabeceras ni después del final
+        assert_eq!(extension(&bmp_header(1000, 20, 40)), None);
+        assert_eq!(extension(&bmp_header(40, 54, 40)), None);
+    }
+
+    #[test]
+    fn text_starting_like_a_signature_is_not_detected() {
+        assert_eq!(extension(b"BMW service history, 2019 to 2024\n"), None);
+        assert_eq!(extension(b"MZ notes: remember to call the plumber on monday\n"), None);
+        assert_eq!(extension(b"BM"), None);
+        assert_eq!(extension(b"MZ"), None);
+    }
+
+    #[test]
+    fn pe_needs_the_signature_at_e_lfanew() {
+        let mut header = vec![0; 256];
+        header[..2].copy_from_slice(b"MZ");
+        header[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        assert_eq!(extension(&header), None);
+
+        header[0x80..0x84].copy_from_slice(b"PE\0\0");
+        assert_eq!(extension(&header), Some("exe"));
+
+        // Un desplazamiento fuera de la cabecera no puede comprobarse
+        header[0x3c..0x40].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(extension(&header), None);
+    }
+}
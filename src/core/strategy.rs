@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::dates::DateGrouping;
+use super::rules::{wildcard_match, RuleSet};
+use super::sniff::DetectedType;
+
+/// Archivo candidato a organizar, con lo que las estrategias necesitan saber de él
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    /// Ruta relativa a la carpeta que se está organizando
+    pub relative_path: PathBuf,
+    pub file_name: String,
+    /// Extensión en minúsculas usada para clasificar. Puede venir del contenido
+    /// si no había extensión o si se pidió dar prioridad al contenido.
+    pub extension: Option<String>,
+    pub mime_type: Option<&'static str>,
+    /// Tipo reconocido por el contenido, si se activó la detección
+    pub detected_type: Option<DetectedType>,
+    /// Indica si `extension` sale del contenido en lugar del nombre
+    pub extension_from_content: bool,
+    pub metadata: Metadata,
+}
+
+impl FileEntry {
+    pub fn size(&self) -> u64 {
+        self.metadata.len()
+    }
+}
+
+/// Qué hacer con un archivo según una estrategia
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placement {
+    /// Ruta de destino relativa a `Organizer/`, incluido el nombre del archivo
+    Destination { relative_path: PathBuf, reason: String },
+    /// Dejar el archivo donde está
+    Skip { reason: String },
+}
+
+impl Placement {
+    /// Destino `carpeta/<nombre original>`
+    pub fn in_folder(folder: impl AsRef<Path>, entry: &FileEntry, reason: impl Into<String>) -> Self {
+        Placement::Destination {
+            relative_path: folder.as_ref().join(&entry.file_name),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn skip(reason: impl Into<String>) -> Self {
+        Placement::Skip { reason: reason.into() }
+    }
+}
+
+/// Criterio para repartir los archivos en carpetas.
+///
+/// Se puede implementar fuera de la biblioteca y pasar en `OrganizeOptions::strategy`
+/// o registrar en un `StrategyRegistry`.
+pub trait OrganizeStrategy: fmt::Debug + Send + Sync {
+    /// Nombre corto, usado en la CLI y en el registro
+    fn name(&self) -> &str;
+
+    /// Comprueba la configuración antes de recorrer ningún archivo
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn place(&self, entry: &FileEntry) -> Placement;
+}
+
+/// Una carpeta por extensión (`PDF`, `JPG`...) y `Sin_Extension` para el resto
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtensionStrategy;
+
+impl OrganizeStrategy for ExtensionStrategy {
+    fn name(&self) -> &str {
+        "extension"
+    }
+
+    fn place(&self, entry: &FileEntry) -> Placement {
+        match &entry.extension {
+            Some(ext) if entry.extension_from_content => {
+                Placement::in_folder(ext.to_uppercase(), entry, format!("contenido detectado .{}", ext))
+            }
+            Some(ext) => Placement::in_folder(ext.to_uppercase(), entry, format!("extensión .{}", ext)),
+            None => Placement::in_folder("Sin_Extension", entry, "archivo sin extensión"),
+        }
+    }
+}
+
+/// Categorías definidas en un `RuleSet`; lo que no encaja va a `fallback`
+/// o, si no hay, a su carpeta de extensión
+#[derive(Debug, Clone, Default)]
+pub struct CategoryStrategy {
+    pub rules: RuleSet,
+}
+
+impl CategoryStrategy {
+    pub fn new(rules: RuleSet) -> Self {
+        CategoryStrategy { rules }
+    }
+}
+
+impl OrganizeStrategy for CategoryStrategy {
+    fn name(&self) -> &str {
+        "category"
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.rules.validate()
+    }
+
+    fn place(&self, entry: &FileEntry) -> Placement {
+        let rule = self.rules.classify(
+            &entry.file_name,
+            entry.extension.as_deref(),
+            entry.mime_type,
+            entry.size(),
+        );
+
+        match (rule, &self.rules.fallback) {
+            (Some(rule), _) => Placement::in_folder(rule.folder_name(), entry, format!("categoría {}", rule.name)),
+            (None, Some(fallback)) => Placement::in_folder(fallback, entry, "sin categoría"),
+            (None, None) => ExtensionStrategy.place(entry),
+        }
+    }
+}
+
+/// Carpetas por fecha según una plantilla (`2025/03`)
+#[derive(Debug, Clone, Default)]
+pub struct DateStrategy {
+    pub grouping: DateGrouping,
+}
+
+impl DateStrategy {
+    pub fn new(grouping: DateGrouping) -> Self {
+        DateStrategy { grouping }
+    }
+}
+
+impl OrganizeStrategy for DateStrategy {
+    fn name(&self) -> &str {
+        "date"
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.grouping.validate()
+    }
+
+    fn place(&self, entry: &FileEntry) -> Placement {
+        match self.grouping.folder_for(&entry.path) {
+            Some((folder, date)) => Placement::in_folder(
+                folder,
+                entry,
+                format!("fecha {} ({})", date.format("%Y-%m-%d"), self.grouping.source),
+            ),
+            None => Placement::in_folder("Sin_Fecha", entry, "fecha no disponible"),
+        }
+    }
+}
+
+/// Tramo de tamaño: los archivos de hasta `max_bytes` van a `folder`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeBucket {
+    pub folder: String,
+    /// `None` = sin límite (último tramo)
+    pub max_bytes: Option<u64>,
+}
+
+/// Carpetas por tamaño; se usa el primer tramo en el que cabe el archivo
+#[derive(Debug, Clone)]
+pub struct SizeBucketStrategy {
+    pub buckets: Vec<SizeBucket>,
+}
+
+impl Default for SizeBucketStrategy {
+    fn default() -> Self {
+        const MB: u64 = 1024 * 1024;
+        let bucket = |folder: &str, max_bytes| SizeBucket { folder: folder.to_string(), max_bytes };
+
+        SizeBucketStrategy {
+            buckets: vec![
+                bucket("Pequenos", Some(MB)),
+                bucket("Medianos", Some(100 * MB)),
+                bucket("Grandes", Some(1024 * MB)),
+                bucket("Enormes", None),
+            ],
+        }
+    }
+}
+
+impl SizeBucketStrategy {
+    /// Nombre del tramo en el que cae un tamaño
+    pub fn bucket_for(&self, size: u64) -> Option<&SizeBucket> {
+        self.buckets
+            .iter()
+            .find(|bucket| bucket.max_bytes.is_none_or(|max| size <= max))
+    }
+}
+
+impl OrganizeStrategy for SizeBucketStrategy {
+    fn name(&self) -> &str {
+        "size"
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.buckets.is_empty() {
+            return Err("No hay tramos de tamaño definidos".to_string());
+        }
+        Ok(())
+    }
+
+    fn place(&self, entry: &FileEntry) -> Placement {
+        match self.bucket_for(entry.size()) {
+            Some(bucket) => Placement::in_folder(&bucket.folder, entry, format!("tamaño {} bytes", entry.size())),
+            None => Placement::skip("no cabe en ningún tramo de tamaño"),
+        }
+    }
+}
+
+/// Patrón sobre el nombre (`*`, `?`) y la carpeta a la que manda los archivos
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamePattern {
+    pub pattern: String,
+    pub folder: String,
+}
+
+impl NamePattern {
+    /// Interpreta `patron=carpeta` (p. ej. `IMG_*=Fotos`)
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (pattern, folder) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("Se esperaba patron=carpeta en '{}'", spec))?;
+
+        let (pattern, folder) = (pattern.trim(), folder.trim());
+        if pattern.is_empty() || folder.is_empty() {
+            return Err(format!("Se esperaba patron=carpeta en '{}'", spec));
+        }
+
+        Ok(NamePattern { pattern: pattern.to_string(), folder: folder.to_string() })
+    }
+}
+
+/// Carpetas según patrones sobre el nombre; los archivos que no coinciden
+/// van a `fallback` o se dejan donde están
+#[derive(Debug, Clone, Default)]
+pub struct NamePatternStrategy {
+    pub patterns: Vec<NamePattern>,
+    pub fallback: Option<String>,
+}
+
+impl OrganizeStrategy for NamePatternStrategy {
+    fn name(&self) -> &str {
+        "pattern"
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.patterns.is_empty() {
+            return Err("No hay patrones de nombre definidos".to_string());
+        }
+        Ok(())
+    }
+
+    fn place(&self, entry: &FileEntry) -> Placement {
+        let file_name = entry.file_name.to_lowercase();
+        let matched = self.patterns
+            .iter()
+            .find(|p| wildcard_match(&p.pattern.to_lowercase(), &file_name));
+
+        match (matched, &self.fallback) {
+            (Some(p), _) => Placement::in_folder(&p.folder, entry, format!("patrón {}", p.pattern)),
+            (None, Some(fallback)) => Placement::in_folder(fallback, entry, "no coincide con ningún patrón"),
+            (None, None) => Placement::skip("no coincide con ningún patrón"),
+        }
+    }
+}
+
+/// Estrategias disponibles por nombre
+#[derive(Debug, Clone, Default)]
+pub struct StrategyRegistry {
+    strategies: BTreeMap<String, Arc<dyn OrganizeStrategy>>,
+}
+
+impl StrategyRegistry {
+    /// Registro con las estrategias incluidas y su configuración por defecto
+    pub fn with_builtins() -> Self {
+        let mut registry = StrategyRegistry::default();
+        registry.register(Arc::new(ExtensionStrategy));
+        registry.register(Arc::new(CategoryStrategy::new(RuleSet::builtin())));
+        registry.register(Arc::new(DateStrategy::default()));
+        registry.register(Arc::new(SizeBucketStrategy::default()));
+        registry
+    }
+
+    /// Añade (o reemplaza) una estrategia bajo su `name()`
+    pub fn register(&mut self, strategy: Arc<dyn OrganizeStrategy>) {
+        self.strategies.insert(strategy.name().to_string(), strategy);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn OrganizeStrategy>> {
+        self.strategies.get(name).cloned()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.strategies.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn registry_finds_builtins_and_registered_strategies() {
+        let mut registry = StrategyRegistry::with_builtins();
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["category", "date", "extension", "size"]);
+        assert_eq!(registry.get("date").unwrap().name(), "date");
+        assert!(registry.get("pattern").is_none());
+        assert!(registry.get("Date").is_none());
+
+        let patterns = NamePatternStrategy { patterns: vec![NamePattern::parse("IMG_*=Fotos").unwrap()], fallback: None };
+        registry.register(Arc::new(patterns));
+        assert!(registry.get("pattern").unwrap().validate().is_ok());
+        assert_eq!(registry.names().count(), 5);
+    }
+
+    #[test]
+    fn name_pattern_specs() {
+        assert_eq!(
+            NamePattern::parse(" IMG_* = Fotos "),
+            Ok(NamePattern { pattern: "IMG_*".to_string(), folder: "Fotos".to_string() })
+        );
+        // Solo el primer `=` separa
+        assert_eq!(NamePattern::parse("a=b=c").unwrap().folder, "b=c");
+        for spec in ["", "IMG_*", "=Fotos", "IMG_*=", " = "] {
+            assert!(NamePattern::parse(spec).is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn size_buckets_include_their_upper_limit() {
+        let strategy = SizeBucketStrategy::default();
+        let folder = |size| strategy.bucket_for(size).map(|bucket| bucket.folder.as_str());
+
+        assert_eq!(folder(0), Some("Pequenos"));
+        assert_eq!(folder(MB), Some("Pequenos"));
+        assert_eq!(folder(MB + 1), Some("Medianos"));
+        assert_eq!(folder(100 * MB), Some("Medianos"));
+        assert_eq!(folder(100 * MB + 1), Some("Grandes"));
+        assert_eq!(folder(1024 * MB), Some("Grandes"));
+        assert_eq!(folder(1024 * MB + 1), Some("Enormes"));
+        assert_eq!(folder(u64::MAX), Some("Enormes"));
+
+        let bounded = SizeBucketStrategy { buckets: strategy.buckets[..1].to_vec() };
+        assert!(bounded.bucket_for(MB + 1).is_none());
+        assert!(SizeBucketStrategy { buckets: Vec::new() }.validate().is_err());
+    }
+}
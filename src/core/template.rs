@@ -0,0 +1,310 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Datelike;
+
+use super::dates::{file_date, DateSource};
+use super::rules::RuleSet;
+use super::strategy::{FileEntry, SizeBucketStrategy};
+use super::transfer::hash_file;
+
+/// Plantilla equivalente a la organización por extensión
+pub const DEFAULT_PATH_TEMPLATE: &str = "{EXT}/{name}";
+
+/// Marcadores admitidos en una plantilla de destino
+pub const TEMPLATE_PLACEHOLDERS: [&str; 13] = [
+    "ext", "EXT", "name", "stem", "category", "year", "month", "day",
+    "size_bucket", "parent", "folder", "hash8", "hash",
+];
+
+/// Ruta de destino dentro de `Organizer/` construida a partir de cada archivo.
+///
+/// El último tramo de la plantilla es el nombre final del archivo, por ejemplo
+/// `{category}/{year}/{stem}_{hash8}.{ext}`. Los tramos que quedan vacíos
+/// (p. ej. `{ext}` en un archivo sin extensión) se omiten.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    pub template: String,
+    /// Categorías usadas por `{category}`
+    pub rules: RuleSet,
+    /// Fecha usada por `{year}`, `{month}` y `{day}`
+    pub date_source: DateSource,
+    /// Tramos usados por `{size_bucket}`
+    pub size_buckets: SizeBucketStrategy,
+}
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        PathTemplate::new(DEFAULT_PATH_TEMPLATE)
+    }
+}
+
+impl PathTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        PathTemplate {
+            template: template.into(),
+            rules: RuleSet::builtin(),
+            date_source: DateSource::default(),
+            size_buckets: SizeBucketStrategy::default(),
+        }
+    }
+
+    /// Marcadores que aparecen en la plantilla, en orden
+    fn placeholders(&self) -> Result<Vec<&str>, String> {
+        let mut found = Vec::new();
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Falta '}}' en la plantilla '{}'", self.template))?;
+            found.push(&rest[start + 1..start + end]);
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(format!("Sobra '}}' en la plantilla '{}'", self.template));
+        }
+        Ok(found)
+    }
+
+    /// Comprueba la plantilla antes de recorrer ningún archivo
+    pub fn validate(&self) -> Result<(), String> {
+        for name in self.placeholders()? {
+            if !TEMPLATE_PLACEHOLDERS.contains(&name) {
+                return Err(format!(
+                    "Marcador desconocido '{{{}}}' en la plantilla (disponibles: {})",
+                    name,
+                    TEMPLATE_PLACEHOLDERS.join(", ")
+                ));
+            }
+        }
+
+        let last = self.template.rsplit(['/', '\\']).next().unwrap_or_default();
+        if self.template.trim().is_empty()
+            || self.template.starts_with(['/', '\\'])
+            || self.template.split(['/', '\\']).any(|part| part.trim() == "..")
+            || last.trim().is_empty()
+        {
+            return Err(format!("Plantilla de destino no válida: '{}'", self.template));
+        }
+
+        if self.template.contains("{category}") {
+            self.rules.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn uses(&self, placeholder: &str) -> bool {
+        self.template.contains(&format!("{{{}}}", placeholder))
+    }
+
+    /// Ruta relativa a `Organizer/` para un archivo. `folder` es la carpeta que
+    /// eligió la estrategia, disponible como `{folder}`.
+    pub fn render(&self, entry: &FileEntry, folder: &Path) -> Result<PathBuf, String> {
+        let date = if self.uses("year") || self.uses("month") || self.uses("day") {
+            file_date(&entry.path, self.date_source)
+        } else {
+            None
+        };
+
+        let hash = if self.uses("hash8") || self.uses("hash") {
+            let hash = hash_file(&entry.path)
+                .map_err(|e| format!("no se pudo calcular su hash: {}", e))?;
+            hash.to_hex().to_string()
+        } else {
+            String::new()
+        };
+
+        let stem = Path::new(&entry.file_name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| entry.file_name.clone());
+
+        // Lo escrito en la plantilla separa carpetas; lo que sale de un marcador
+        // es siempre parte de un solo nombre
+        let mut parts = vec![String::new()];
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            push_literal(&mut parts, &rest[..start]);
+            let end = start + rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Falta '}}' en la plantilla '{}'", self.template))?;
+            let name = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+
+            let value = match name {
+                "ext" => entry.extension.clone().unwrap_or_default(),
+                "EXT" => entry.extension
+                    .as_ref()
+                    .map(|ext| ext.to_uppercase())
+                    .unwrap_or_else(|| "Sin_Extension".to_string()),
+                "name" => entry.file_name.clone(),
+                "stem" => stem.clone(),
+                "category" => self.category(entry),
+                "year" => date.map(|d| format!("{:04}", d.year())).unwrap_or_else(|| "Sin_Fecha".to_string()),
+                "month" => date.map(|d| format!("{:02}", d.month())).unwrap_or_default(),
+                "day" => date.map(|d| format!("{:02}", d.day())).unwrap_or_default(),
+                "size_bucket" => self.size_buckets
+                    .bucket_for(entry.size())
+                    .map(|bucket| bucket.folder.clone())
+                    .unwrap_or_default(),
+                "parent" => entry.path
+                    .parent()
+                    .and_then(Path::file_name)
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                "hash8" => hash[..8].to_string(),
+                "hash" => hash.clone(),
+                // La carpeta de la estrategia puede tener varios niveles (`2025/03`)
+                "folder" => {
+                    push_literal(&mut parts, &folder.to_string_lossy());
+                    continue;
+                }
+                _ => return Err(format!("marcador desconocido '{{{}}}'", name)),
+            };
+
+            let part = parts.last_mut().expect("siempre hay un tramo en curso");
+            // Sin extensión, `{stem}.{ext}` no deja un punto suelto al final
+            if name == "ext" && value.is_empty() && part.ends_with('.') {
+                part.pop();
+            }
+            part.push_str(&sanitize_component(&value));
+        }
+        push_literal(&mut parts, rest);
+
+        let parts: Vec<String> = parts
+            .into_iter()
+            .map(|part| finish_component(&part))
+            .filter(|part| !part.is_empty() && part != ".")
+            .collect();
+
+        if parts.is_empty() {
+            return Err(format!("la plantilla '{}' no produce ningún nombre", self.template));
+        }
+        Ok(parts.iter().collect())
+    }
+
+    fn category(&self, entry: &FileEntry) -> String {
+        let rule = self.rules.classify(
+            &entry.file_name,
+            entry.extension.as_deref(),
+            entry.mime_type,
+            entry.size(),
+        );
+        match (rule, &self.rules.fallback) {
+            (Some(rule), _) => rule.folder_name().to_string(),
+            (None, Some(fallback)) => fallback.clone(),
+            (None, None) => "Otros".to_string(),
+        }
+    }
+}
+
+/// Añade texto de la plantilla: cada `/` o `\` empieza un tramo nuevo
+fn push_literal(parts: &mut Vec<String>, text: &str) {
+    let mut pieces = text.split(['/', '\\']);
+    if let (Some(first), Some(part)) = (pieces.next(), parts.last_mut()) {
+        part.push_str(first);
+    }
+    parts.extend(pieces.map(str::to_string));
+}
+
+/// Cambia por `_` los caracteres que no pueden ir en un nombre de archivo de este sistema
+#[cfg(windows)]
+fn sanitize_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn sanitize_component(value: &str) -> String {
+    value.replace(['/', '\0'], "_")
+}
+
+/// Ajusta un tramo ya completo: `..` no puede ser un nombre y Windows no admite
+/// nombres acabados en punto o espacio
+fn finish_component(part: &str) -> String {
+    if part == ".." {
+        return "_".to_string();
+    }
+    if cfg!(windows) {
+        part.trim_end_matches(['.', ' ']).to_string()
+    } else {
+        part.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::core::testing::TempDir;
+
+    /// Entrada de un archivo real creado en la carpeta temporal de la prueba
+    fn entry(dir: &TempDir, file_name: &str) -> FileEntry {
+        let path = dir.write(file_name, "contenido");
+
+        let extension = Path::new(file_name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        FileEntry {
+            metadata: fs::metadata(&path).unwrap(),
+            relative_path: PathBuf::from(file_name),
+            file_name: file_name.to_string(),
+            path,
+            extension,
+            mime_type: None,
+            detected_type: None,
+            extension_from_content: false,
+        }
+    }
+
+    fn render(template: &str, entry: &FileEntry) -> PathBuf {
+        PathTemplate::new(template).render(entry, Path::new("TXT")).unwrap()
+    }
+
+    #[test]
+    fn names_keep_their_spaces_and_dots() {
+        let dir = TempDir::new("template-names");
+        let leading = entry(&dir, " notes.txt");
+        assert_eq!(render("{EXT}/{name}", &leading), Path::new("TXT/ notes.txt"));
+
+        let trailing = entry(&dir, "draft .md");
+        assert_eq!(render("{stem}/{name}", &trailing), Path::new("draft /draft .md"));
+
+        let dotted = entry(&dir, "v1..txt");
+        assert_eq!(render("{folder}/{stem}.{ext}", &dotted), Path::new("TXT/v1..txt"));
+    }
+
+    #[test]
+    fn empty_parts_and_missing_extension_are_dropped() {
+        let dir = TempDir::new("template-empty");
+        let plain = entry(&dir, "README");
+        assert_eq!(render("{folder}/{stem}.{ext}", &plain), Path::new("TXT/README"));
+        assert_eq!(render("{ext}/{name}", &plain), Path::new("README"));
+        assert_eq!(render("{ext}//{name}", &plain), Path::new("README"));
+    }
+
+    #[test]
+    fn values_never_add_folders() {
+        let dir = TempDir::new("template-values");
+        let file = entry(&dir, "a.txt");
+        let mut template = PathTemplate::new("{category}/{name}");
+        template.rules.categories.clear();
+        template.rules.fallback = Some("x/../y".to_string());
+        assert_eq!(template.render(&file, Path::new("TXT")).unwrap(), Path::new("x_.._y/a.txt"));
+
+        template.rules.fallback = Some("..".to_string());
+        assert_eq!(template.render(&file, Path::new("TXT")).unwrap(), Path::new("_/a.txt"));
+    }
+
+    #[test]
+    fn unterminated_placeholder_is_an_error() {
+        let dir = TempDir::new("template-unterminated");
+        let file = entry(&dir, "a.txt");
+        for template in ["{ext", "{EXT}/{name"] {
+            let error = PathTemplate::new(template).render(&file, Path::new("TXT")).unwrap_err();
+            assert!(error.contains("Falta '}'"), "{}", error);
+        }
+    }
+}
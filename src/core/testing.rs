@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Carpeta temporal de una prueba, que se borra al terminar
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Carpeta vacía para la prueba `name`, que tiene que ser único entre todas las pruebas
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("organizer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub(crate) fn join(&self, relative: impl AsRef<Path>) -> PathBuf {
+        self.0.join(relative)
+    }
+
+    /// Crea el archivo `relative` con `contents`, y las carpetas que le falten
+    pub(crate) fn write(&self, relative: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Contenido del archivo `relative`
+    pub(crate) fn read(&self, relative: impl AsRef<Path>) -> String {
+        fs::read_to_string(self.join(relative)).unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,450 @@
+use std::fs::{self, File, FileTimes};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::cancel::CancelToken;
+
+/// Tamaño del bloque usado al copiar archivos por partes
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Mueve un archivo. Si `rename` falla porque origen y destino están en
+/// sistemas de archivos distintos, copia, sincroniza, verifica y borra el origen.
+/// Devuelve el número de bytes del archivo.
+pub(crate) fn move_file(source: &Path, destination: &Path, verify: bool) -> io::Result<u64> {
+    move_file_with_cancel(source, destination, verify, &CancelToken::new())
+}
+
+/// Como `move_file`; si se cancela durante la copia entre discos el origen no se toca
+pub(crate) fn move_file_with_cancel(source: &Path, destination: &Path, verify: bool, cancel: &CancelToken) -> io::Result<u64> {
+    // Un enlace se recrea en el destino: `rename` conservaría un destino relativo que ya no valdría
+    if fs::symlink_metadata(source)?.is_symlink() {
+        let size = copy_link(source, destination)?;
+        if let Err(e) = fs::remove_file(source) {
+            let _ = fs::remove_file(destination);
+            return Err(e);
+        }
+        return Ok(size);
+    }
+
+    let size = fs::metadata(source)?.len();
+
+    match fs::rename(source, destination) {
+        Ok(_) => Ok(size),
+        Err(e) if is_cross_device(&e) => move_by_copy(source, destination, verify, cancel, |source| fs::remove_file(source)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Mueve copiando y borrando después el origen. Lo que hubiera en el destino se
+/// aparta hasta que el origen está borrado: si no se puede borrar, se deshace
+/// la copia y el destino vuelve a quedar como estaba.
+fn move_by_copy<F>(source: &Path, destination: &Path, verify: bool, cancel: &CancelToken, remove_source: F) -> io::Result<u64>
+where
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    let partial = partial_path(destination);
+    let size = copy_to_verified_partial(source, &partial, verify, cancel)?;
+
+    let replaced = replaced_path(destination);
+    let had_destination = fs::symlink_metadata(destination).is_ok();
+    if had_destination && let Err(e) = fs::rename(destination, &replaced) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    let restore = || {
+        if had_destination {
+            let _ = fs::rename(&replaced, destination);
+        }
+    };
+
+    if let Err(e) = finish_partial(&partial, destination) {
+        restore();
+        return Err(e);
+    }
+    if let Err(e) = remove_source(source) {
+        let _ = fs::remove_file(destination);
+        restore();
+        return Err(e);
+    }
+    if had_destination {
+        let _ = fs::remove_file(&replaced);
+    }
+    Ok(size)
+}
+
+/// Copia un archivo a través de un temporal en la carpeta de destino, de modo que
+/// nunca queda una copia a medias con el nombre final. Conserva permisos y fechas.
+pub(crate) fn copy_file(source: &Path, destination: &Path, verify: bool) -> io::Result<u64> {
+    copy_file_with_cancel(source, destination, verify, &CancelToken::new())
+}
+
+/// Como `copy_file`, pero entre bloques comprueba `cancel`; si se cancela, borra
+/// el temporal y devuelve un error `Interrupted`
+pub(crate) fn copy_file_with_cancel(source: &Path, destination: &Path, verify: bool, cancel: &CancelToken) -> io::Result<u64> {
+    // De un enlace se copia el enlace, no el contenido al que apunta
+    if fs::symlink_metadata(source)?.is_symlink() {
+        return copy_link(source, destination);
+    }
+
+    let partial = partial_path(destination);
+    let size = copy_to_verified_partial(source, &partial, verify, cancel)?;
+    finish_partial(&partial, destination)?;
+    Ok(size)
+}
+
+/// Copia `source` al temporal `partial` y, si se pide, comprueba el temporal
+/// contra el original. Si algo falla, el temporal se borra y lo que hubiera en
+/// el destino sigue intacto.
+fn copy_to_verified_partial(source: &Path, partial: &Path, verify: bool, cancel: &CancelToken) -> io::Result<u64> {
+    let verified = copy_to_partial(source, partial, verify, cancel).and_then(|(size, source_hash)| {
+        match source_hash {
+            Some(source_hash) if hash_file(partial)? != source_hash => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "la copia no coincide con el original",
+            )),
+            _ => Ok(size),
+        }
+    });
+    verified.inspect_err(|_| {
+        let _ = fs::remove_file(partial);
+    })
+}
+
+/// Da al temporal su nombre final y sincroniza la carpeta, para que el cambio
+/// de nombre no se pierda en un corte de luz
+fn finish_partial(partial: &Path, destination: &Path) -> io::Result<()> {
+    fs::rename(partial, destination).inspect_err(|_| {
+        let _ = fs::remove_file(partial);
+    })?;
+    // Si el sistema de archivos no lo permite, el archivo ya está en su sitio igualmente
+    let _ = sync_parent(destination);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(parent)?.sync_all()
+}
+
+/// Fuera de Unix una carpeta no se puede abrir para sincronizarla
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Sustituye `path` por un enlace (duro o simbólico) a `target`. El enlace se
+/// crea con un nombre temporal y se renombra encima, así `path` nunca desaparece.
+pub(crate) fn replace_with_link(path: &Path, target: &Path, symbolic: bool) -> io::Result<()> {
+    let partial = partial_path(path);
+    let _ = fs::remove_file(&partial);
+
+    if symbolic {
+        symlink_file(&fs::canonicalize(target)?, &partial)?;
+    } else {
+        fs::hard_link(target, &partial)?;
+    }
+
+    fs::rename(&partial, path).inspect_err(|_| {
+        let _ = fs::remove_file(&partial);
+    })
+}
+
+/// Crea en `destination` un enlace simbólico al mismo sitio que `source`. Un
+/// destino relativo se conserva si desde la nueva carpeta lleva al mismo
+/// archivo; si no, se convierte en absoluto. Devuelve el tamaño del enlace.
+fn copy_link(source: &Path, destination: &Path) -> io::Result<u64> {
+    let target = fs::read_link(source)?;
+    let target = match (source.parent(), destination.parent()) {
+        (Some(parent), Some(new_parent)) if target.is_relative() => {
+            let resolved = fs::canonicalize(parent.join(&target)).ok();
+            if resolved.is_some() && resolved == fs::canonicalize(new_parent.join(&target)).ok() {
+                target
+            } else {
+                std::path::absolute(parent.join(target))?
+            }
+        }
+        _ => target,
+    };
+
+    create_link(&target, destination)?;
+    Ok(fs::symlink_metadata(destination)?.len())
+}
+
+/// Sustituye el enlace `link` por uno en `destination` que apunta exactamente a
+/// `target`; al deshacer, devuelve el enlace a su sitio tal como era
+pub(crate) fn move_link(link: &Path, destination: &Path, target: &Path) -> io::Result<()> {
+    if !fs::symlink_metadata(link)?.is_symlink() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "ya no es un enlace simbólico"));
+    }
+    create_link(target, destination)?;
+    fs::remove_file(link).inspect_err(|_| {
+        let _ = fs::remove_file(destination);
+    })
+}
+
+/// Crea el enlace con un nombre temporal y lo renombra a `destination`
+fn create_link(target: &Path, destination: &Path) -> io::Result<()> {
+    let partial = partial_path(destination);
+    let _ = fs::remove_file(&partial);
+    symlink_file(target, &partial)?;
+    fs::rename(&partial, destination).inspect_err(|_| {
+        let _ = fs::remove_file(&partial);
+    })
+}
+
+#[cfg(unix)]
+fn symlink_file(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink_file(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "enlaces simbólicos no disponibles"))
+}
+
+/// Calcula el hash BLAKE3 del contenido de un archivo
+pub(crate) fn hash_file(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+fn copy_to_partial(
+    source: &Path,
+    partial: &Path,
+    verify: bool,
+    cancel: &CancelToken,
+) -> io::Result<(u64, Option<blake3::Hash>)> {
+    let mut reader = File::open(source)?;
+    let metadata = reader.metadata()?;
+    let mut writer = File::create(partial)?;
+
+    let mut hasher = verify.then(blake3::Hasher::new);
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut size = 0;
+
+    loop {
+        if cancel.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "copia cancelada"));
+        }
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&buffer[..read]);
+        }
+        size += read as u64;
+    }
+
+    // Conservar fechas y permisos del original antes de cerrar el archivo
+    let mut times = FileTimes::new();
+    if let Ok(modified) = metadata.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    writer.set_times(times)?;
+    writer.set_permissions(metadata.permissions())?;
+    writer.sync_all()?;
+
+    Ok((size, hasher.map(|h| h.finalize())))
+}
+
+/// `carpeta/nombre.ext` -> `carpeta/.nombre.ext.organizer-part`
+fn partial_path(destination: &Path) -> PathBuf {
+    hidden_sibling(destination, "organizer-part")
+}
+
+/// `carpeta/nombre.ext` -> `carpeta/.nombre.ext.organizer-old`, donde espera el
+/// archivo sobrescrito hasta que el movimiento termina
+fn replaced_path(destination: &Path) -> PathBuf {
+    hidden_sibling(destination, "organizer-old")
+}
+
+fn hidden_sibling(path: &Path, suffix: &str) -> PathBuf {
+    let file_name = path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}", file_name, suffix))
+}
+
+fn is_cross_device(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::CrossesDevices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::testing::TempDir;
+
+    /// Contenido de más de un bloque para que la copia dé varias vueltas
+    fn content() -> Vec<u8> {
+        (0..CHUNK_SIZE * 2 + 123).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn copy_keeps_content_and_modified_time() {
+        let dir = TempDir::new("transfer-copy");
+        let source = dir.write("a.bin", content());
+        let destination = dir.join("b.bin");
+
+        for verify in [false, true] {
+            let copied = copy_file(&source, &destination, verify).unwrap();
+            assert_eq!(copied, content().len() as u64);
+            assert_eq!(fs::read(&destination).unwrap(), content());
+            assert_eq!(
+                fs::metadata(&destination).unwrap().modified().unwrap(),
+                fs::metadata(&source).unwrap().modified().unwrap(),
+            );
+            assert!(!partial_path(&destination).exists());
+        }
+        assert_eq!(hash_file(&source).unwrap(), hash_file(&destination).unwrap());
+    }
+
+    #[test]
+    fn failed_rename_removes_the_partial_file() {
+        let dir = TempDir::new("transfer-rename");
+        let source = dir.write("a.txt", "contenido");
+        // Una carpeta con el nombre de destino impide el último paso
+        let destination = dir.join("b.txt");
+        dir.write("b.txt/dentro.txt", "x");
+
+        assert!(copy_file(&source, &destination, true).is_err());
+        assert!(!partial_path(&destination).exists());
+        assert!(destination.join("dentro.txt").exists());
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn cancelled_copy_leaves_nothing_behind() {
+        let dir = TempDir::new("transfer-cancel");
+        let source = dir.write("a.bin", content());
+        let destination = dir.join("b.bin");
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let error = copy_file_with_cancel(&source, &destination, true, &cancel).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        assert!(!destination.exists());
+        assert!(!partial_path(&destination).exists());
+
+        // Un `rename` en el mismo disco no copia, así que no llega a cancelarse
+        move_file_with_cancel(&source, &destination, true, &cancel).unwrap();
+        assert_eq!(fs::read(&destination).unwrap(), content());
+        assert!(!source.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cancel_mid_copy_removes_the_partial_file() {
+        let dir = TempDir::new("transfer-midcopy");
+        let source = dir.join("pipe");
+        let destination = dir.join("b.bin");
+        let partial = partial_path(&destination);
+        // Con una tubería la prueba decide cuándo llega cada bloque
+        assert!(std::process::Command::new("mkfifo").arg(&source).status().unwrap().success());
+
+        let cancel = CancelToken::new();
+        let writer = {
+            let (source, partial, cancel) = (source.clone(), partial.clone(), cancel.clone());
+            std::thread::spawn(move || {
+                let mut pipe = fs::OpenOptions::new().write(true).open(&source).unwrap();
+                pipe.write_all(b"primer bloque").unwrap();
+                while fs::metadata(&partial).map_or(true, |m| m.len() == 0) {
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                }
+                cancel.cancel();
+                pipe.write_all(b"segundo bloque").unwrap();
+            })
+        };
+
+        let error = copy_file_with_cancel(&source, &destination, true, &cancel).unwrap_err();
+        writer.join().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        assert!(!destination.exists());
+        assert!(!partial.exists());
+    }
+
+    #[test]
+    fn existing_destination_is_replaced_whole() {
+        let dir = TempDir::new("transfer-replace");
+        let source = dir.write("a.txt", "nuevo");
+        let destination = dir.write("b.txt", "contenido anterior mas largo");
+
+        move_file(&source, &destination, true).unwrap();
+        assert_eq!(dir.read("b.txt"), "nuevo");
+        assert!(!source.exists());
+    }
+
+    #[test]
+    fn move_between_disks_keeps_the_overwritten_file_until_the_source_is_gone() {
+        let dir = TempDir::new("transfer-overwrite");
+        let source = dir.write("a.txt", "nuevo");
+        let destination = dir.write("b.txt", "original");
+        let cancel = CancelToken::new();
+        let leftovers = || [partial_path(&destination), replaced_path(&destination)].iter().any(|p| p.exists());
+
+        // Si el origen no se puede borrar, todo queda como estaba
+        let error = move_by_copy(&source, &destination, true, &cancel, |_| Err(io::ErrorKind::PermissionDenied.into()))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(dir.read("a.txt"), "nuevo");
+        assert_eq!(dir.read("b.txt"), "original");
+        assert!(!leftovers());
+
+        // Sin nada en el destino, deshacer es borrar la copia
+        let fresh = dir.join("c.txt");
+        assert!(move_by_copy(&source, &fresh, false, &cancel, |_| Err(io::ErrorKind::PermissionDenied.into())).is_err());
+        assert!(!fresh.exists());
+
+        move_by_copy(&source, &destination, true, &cancel, |source| fs::remove_file(source)).unwrap();
+        assert_eq!(dir.read("b.txt"), "nuevo");
+        assert!(!source.exists());
+        assert!(!leftovers());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn moved_links_keep_relative_targets_that_still_resolve() {
+        let dir = TempDir::new("transfer-links");
+        fs::create_dir_all(dir.join("src/sub")).unwrap();
+        fs::create_dir_all(dir.join("out/sub")).unwrap();
+        dir.write("shared/t.txt", "hola");
+        symlink_file(Path::new("../../shared/t.txt"), &dir.join("src/sub/same.txt")).unwrap();
+        symlink_file(Path::new("../shared/t.txt"), &dir.join("src/up.txt")).unwrap();
+
+        // Misma profundidad: el destino relativo sigue valiendo
+        move_file(&dir.join("src/sub/same.txt"), &dir.join("out/sub/same.txt"), false).unwrap();
+        assert_eq!(fs::read_link(dir.join("out/sub/same.txt")).unwrap(), Path::new("../../shared/t.txt"));
+
+        // Otra profundidad: pasa a absoluto y sigue llevando al mismo archivo
+        move_file(&dir.join("src/up.txt"), &dir.join("out/sub/up.txt"), false).unwrap();
+        let target = fs::read_link(dir.join("out/sub/up.txt")).unwrap();
+        assert!(target.is_absolute());
+        assert_eq!(dir.read("out/sub/up.txt"), "hola");
+
+        // Deshacer devuelve el enlace tal como era
+        move_link(&dir.join("out/sub/up.txt"), &dir.join("src/up.txt"), Path::new("../shared/t.txt")).unwrap();
+        assert_eq!(fs::read_link(dir.join("src/up.txt")).unwrap(), Path::new("../shared/t.txt"));
+        assert!(fs::symlink_metadata(dir.join("out/sub/up.txt")).is_err());
+    }
+}
@@ -0,0 +1,2 @@
+//! Lógica compartida entre la interfaz gráfica y la CLI de oRganizer
+pub mod core;
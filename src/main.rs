@@ -1,5 +1,4 @@
 mod ui;
-mod core;
 use eframe::Result;
 
 fn main() -> Result<()> {
@@ -1,32 +1,182 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
 use eframe::egui;
 use eframe::egui::RichText;
 use rfd::FileDialog;
-use crate::core::{FileOrganizerCore, OrganizerResult, FileOperationMode};
+
+use organizer::core::{
+    CancelToken, ConflictPolicy, FileOperationMode, FileOrganizerCore, OrganizeOptions, OrganizePlan, OrganizerError,
+    OrganizerResult, Progress, UndoResult,
+};
+use organizer::core::{count_by_ignore_file, format_size, DateBound, FileFilter, ScanOptions, SymlinkPolicy};
+use organizer::core::{
+    CategoryStrategy, DateGrouping, DateSource, DateStrategy, ExtensionStrategy, NamePattern, NamePatternStrategy,
+    OrganizeStrategy, PathTemplate, RuleSet, SizeBucketStrategy, TypeDetection,
+};
+use organizer::core::{DuplicateAction, DuplicateReport, KeepRule};
+use organizer::core::{ReportFormat, RunReport};
+
+/// Criterio de agrupación elegido en la interfaz
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Agrupacion {
+    Extension,
+    Categoria,
+    Fecha,
+    Tamano,
+    Patron,
+}
+
+impl Agrupacion {
+    const TODAS: [Agrupacion; 5] = [
+        Agrupacion::Extension,
+        Agrupacion::Categoria,
+        Agrupacion::Fecha,
+        Agrupacion::Tamano,
+        Agrupacion::Patron,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Agrupacion::Extension => "Extension",
+            Agrupacion::Categoria => "Categoria",
+            Agrupacion::Fecha => "Fecha",
+            Agrupacion::Tamano => "Tamano",
+            Agrupacion::Patron => "Nombre",
+        }
+    }
+}
+
+/// Pestaña activa de la ventana
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pestana {
+    Organizar,
+    Duplicados,
+}
+
+/// Mensajes del hilo que hace el trabajo en curso
+enum EventoTrabajo {
+    Progreso(Progress),
+    /// Plan calculado, para la vista previa
+    Plan(Box<Result<OrganizePlan, OrganizerError>>),
+    /// Resultado de aplicar un plan, con la carpeta y el modo del plan
+    Terminado(Box<Result<OrganizerResult, OrganizerError>>, PathBuf, FileOperationMode),
+    Duplicados(Box<Result<DuplicateReport, OrganizerError>>),
+    /// Resumen de la resolución de duplicados; le sigue una nueva búsqueda
+    Resueltos(String),
+    /// Resultado de deshacer la última ejecución y si le sigue una búsqueda de duplicados
+    Deshecho(Box<Result<UndoResult, OrganizerError>>, bool),
+}
+
+/// Planificación, organización, búsqueda de duplicados o deshacer en curso en un hilo aparte
+pub struct Trabajo {
+    receptor: Receiver<EventoTrabajo>,
+    /// Qué se está haciendo, como título de la barra de progreso
+    tarea: &'static str,
+    /// Último avance recibido (None hasta que empieza el primer archivo)
+    progreso: Option<Progress>,
+    raiz: PathBuf,
+    /// Token para el botón "Cancelar" (None si el trabajo no se puede cancelar)
+    cancelar: Option<CancelToken>,
+}
 
 pub struct ORganizer {
+    pub pestana: Pestana,
     pub ruta_seleccionada: String,
+    /// Carpeta de destino (vacía = `Organizer/` dentro de la ruta seleccionada)
+    pub carpeta_destino: String,
     pub archivos_listados: Vec<String>,
     pub resultado_organizacion: Option<OrganizerResult>,
+    /// Carpeta y modo de la ejecución de `resultado_organizacion`, para el informe
+    pub origen_resultado: Option<(PathBuf, FileOperationMode)>,
     pub mostrar_resumen: bool,
+    /// Resultado de la última exportación del informe
+    pub mensaje_informe: Option<String>,
+    /// Organización que se está ejecutando, si hay alguna
+    pub trabajo: Option<Trabajo>,
     pub exclude_folders: String,
+    /// Patrones de los únicos archivos que se organizan (vacío = todos)
+    pub include_patterns: String,
+    /// Profundidad del recorrido de subcarpetas y filtros
+    pub escaneo: ScanOptions,
+    /// Patrones de nombre del filtro, separados por coma
+    pub filtro_nombres: String,
     pub operation_mode: FileOperationMode,
+    pub plan_pendiente: Option<OrganizePlan>,
+    pub mensaje_deshacer: Option<String>,
+    pub conflict_policy: ConflictPolicy,
+    /// Decisión tomada en la vista previa para cada conflicto (política `Ask`)
+    pub decisiones_conflicto: HashMap<PathBuf, ConflictPolicy>,
+    pub verificar_copias: bool,
+    /// Archivos que se transfieren a la vez
+    pub trabajos_paralelos: usize,
+    pub agrupacion: Agrupacion,
+    pub reglas: RuleSet,
+    /// Archivo del que se cargaron las reglas (None = categorías por defecto)
+    pub archivo_reglas: Option<String>,
+    pub deteccion_tipo: TypeDetection,
+    pub agrupacion_fecha: DateGrouping,
+    /// Patrones `patron=carpeta` separados por coma
+    pub patrones_nombre: String,
+    /// Plantilla de la ruta de destino (vacía = la de la estrategia)
+    pub plantilla_destino: String,
+    /// Última búsqueda de duplicados
+    pub duplicados: Option<Result<DuplicateReport, OrganizerError>>,
+    pub regla_conservar: KeepRule,
+    /// Carpeta cuyas copias se conservan (regla "La de una carpeta")
+    pub carpeta_preferida: String,
+    pub accion_duplicados: DuplicateAction,
+    /// Resumen de la última resolución de duplicados
+    pub mensaje_duplicados: Option<String>,
 }
 
 impl ORganizer {
     pub fn new() -> Self {
         Self {
+            pestana: Pestana::Organizar,
             ruta_seleccionada: String::new(),
+            carpeta_destino: String::new(),
             archivos_listados: Vec::new(),
             resultado_organizacion: None,
+            origen_resultado: None,
             mostrar_resumen: false,
+            mensaje_informe: None,
+            trabajo: None,
             exclude_folders: String::new(),
+            include_patterns: String::new(),
+            escaneo: ScanOptions::default(),
+            filtro_nombres: String::new(),
             operation_mode: FileOperationMode::Cut,
+            plan_pendiente: None,
+            mensaje_deshacer: None,
+            conflict_policy: ConflictPolicy::default(),
+            decisiones_conflicto: HashMap::new(),
+            verificar_copias: false,
+            trabajos_paralelos: 1,
+            agrupacion: Agrupacion::Extension,
+            reglas: RuleSet::builtin(),
+            archivo_reglas: None,
+            deteccion_tipo: TypeDetection::Off,
+            agrupacion_fecha: DateGrouping::default(),
+            patrones_nombre: String::new(),
+            plantilla_destino: String::new(),
+            duplicados: None,
+            regla_conservar: KeepRule::default(),
+            carpeta_preferida: String::new(),
+            accion_duplicados: DuplicateAction::default(),
+            mensaje_duplicados: None,
         }
     }
 }
 
 impl eframe::App for ORganizer {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.recibir_trabajo();
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // Título grande
             ui.add(egui::Label::new(
@@ -36,34 +186,122 @@ impl eframe::App for ORganizer {
             // Descripción
             ui.label("Selecciona una carpeta para organizar archivos por extension.");
 
-            ui.add_space(15.0);
+            // Mientras se organiza, el resto de la ventana queda deshabilitado
+            if self.trabajo.is_some() {
+                self.mostrar_progreso(ui);
+                ui.disable();
+            }
+
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.pestana, Pestana::Organizar, "Organizar");
+                ui.selectable_value(&mut self.pestana, Pestana::Duplicados, "Duplicados");
+            });
+
+            ui.add_space(10.0);
 
             // Selector de ruta
             ui.horizontal(|ui| {
                 ui.label("Ruta:");
-                if ui.button("Seleccionar Carpeta...").clicked() {
-                    if let Some(path) = FileDialog::new().pick_folder() {
-                        self.ruta_seleccionada = path.to_string_lossy().to_string();
-                        self.archivos_listados.clear(); // Limpiar lista anterior
-                        self.resultado_organizacion = None;
-                        self.mostrar_resumen = false;
-                    }
+                if ui.button("Seleccionar Carpeta...").clicked()
+                    && let Some(path) = FileDialog::new().pick_folder()
+                {
+                    self.ruta_seleccionada = path.to_string_lossy().to_string();
+                    self.archivos_listados.clear(); // Limpiar lista anterior
+                    self.resultado_organizacion = None;
+                    self.origen_resultado = None;
+                    self.mostrar_resumen = false;
+                    self.plan_pendiente = None;
+                    self.duplicados = None;
                 }
             });
 
             // Mostrar ruta seleccionada
             ui.text_edit_singleline(&mut self.ruta_seleccionada).sense.interactive();
 
+            // Selector de la carpeta de destino
+            ui.horizontal(|ui| {
+                ui.label("Destino:");
+                if ui.button("Seleccionar Destino...").clicked()
+                    && let Some(path) = FileDialog::new().pick_folder()
+                {
+                    self.carpeta_destino = path.to_string_lossy().to_string();
+                    self.plan_pendiente = None;
+                }
+                if !self.carpeta_destino.is_empty() && ui.button("Usar Organizer/").clicked() {
+                    self.carpeta_destino.clear();
+                    self.plan_pendiente = None;
+                }
+            });
+            ui.add(egui::TextEdit::singleline(&mut self.carpeta_destino).hint_text("Organizer/ dentro de la ruta seleccionada"));
+
             ui.add_space(15.0);
 
             // Campo para excluir archivos/carpetas
             ui.horizontal(|ui| {
                 ui.label("Archivos/Carpetas a excluir (separados por coma):");
             });
-            ui.text_edit_singleline(&mut self.exclude_folders);
+            ui.add(egui::TextEdit::singleline(&mut self.exclude_folders).hint_text("*.part, node_modules/, docs/keep.pdf, re:^tmp_"));
+
+            ui.horizontal(|ui| {
+                ui.label("Organizar solo (separados por coma, vacio = todo):");
+            });
+            ui.add(egui::TextEdit::singleline(&mut self.include_patterns).hint_text("*.pdf, fotos/**"));
+
+            // Profundidad del recorrido
+            ui.horizontal(|ui| {
+                ui.label("Niveles de subcarpetas:");
+                let mut sin_limite = self.escaneo.max_depth.is_none();
+                if ui.checkbox(&mut sin_limite, "Sin limite").changed() {
+                    self.escaneo.max_depth = if sin_limite { None } else { Some(0) };
+                }
+                if let Some(niveles) = &mut self.escaneo.max_depth {
+                    ui.add(egui::DragValue::new(niveles).range(0..=64));
+                    ui.label("(0 = solo la carpeta seleccionada)");
+                }
+            });
+            ui.checkbox(&mut self.escaneo.ignore_files, "Respetar los archivos .organizerignore");
+            ui.horizontal(|ui| {
+                ui.label("Enlaces simbolicos:");
+                egui::ComboBox::from_id_salt("enlaces_simbolicos")
+                    .selected_text(self.escaneo.symlinks.label())
+                    .show_ui(ui, |ui| {
+                        for policy in SymlinkPolicy::ALL {
+                            ui.selectable_value(&mut self.escaneo.symlinks, policy, policy.label());
+                        }
+                    });
+            });
+
+            // Filtros por tamaño, antigüedad, nombre y ocultos
+            egui::CollapsingHeader::new("Filtros").show(ui, |ui| {
+                let filtro = &mut self.escaneo.filter;
+                ui.horizontal(|ui| {
+                    editar_tamano(ui, "Tamano minimo", &mut filtro.min_size);
+                    editar_tamano(ui, "Tamano maximo", &mut filtro.max_size);
+                });
+                ui.horizontal(|ui| {
+                    editar_dias(ui, "Modificados hace mas de", &mut filtro.modified_before);
+                    editar_dias(ui, "Modificados hace menos de", &mut filtro.modified_after);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Nombres (separados por coma):");
+                    ui.add(egui::TextEdit::singleline(&mut self.filtro_nombres).hint_text("*.pdf, IMG_*"));
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut filtro.include_hidden, "Incluir archivos ocultos");
+                    ui.checkbox(&mut filtro.match_any, "Basta con cumplir un criterio");
+                });
+            });
 
             ui.add_space(10.0);
 
+            // Los duplicados se buscan con las mismas opciones de recorrido
+            if self.pestana == Pestana::Duplicados {
+                self.mostrar_duplicados(ui);
+                return;
+            }
+
             // Operation mode selection
             ui.label(RichText::new("Modo de operacion:").strong());
             ui.horizontal(|ui| {
@@ -76,8 +314,92 @@ impl eframe::App for ORganizer {
                 if copy_selected {
                     println!("Modo Copiar seleccionado - Los archivos se copiaran");
                 }
+
+                ui.separator();
+                ui.label("Si el archivo ya existe:");
+                egui::ComboBox::from_id_salt("conflict_policy")
+                    .selected_text(self.conflict_policy.label())
+                    .show_ui(ui, |ui| {
+                        for policy in ConflictPolicy::ALL {
+                            ui.selectable_value(&mut self.conflict_policy, policy, policy.label());
+                        }
+                    });
+            });
+            ui.checkbox(&mut self.verificar_copias, "Verificar cada copia con un hash (mas lento)");
+            ui.horizontal(|ui| {
+                ui.label("Archivos a la vez:");
+                ui.add(egui::DragValue::new(&mut self.trabajos_paralelos).range(1..=32));
+                ui.label("(mas de 1 acelera discos rapidos y rutas de red)");
+            });
+
+            // Criterio de agrupación
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Agrupar por:").strong());
+                for agrupacion in Agrupacion::TODAS {
+                    ui.selectable_value(&mut self.agrupacion, agrupacion, agrupacion.label());
+                }
             });
 
+            match self.agrupacion {
+                Agrupacion::Extension => {}
+                Agrupacion::Categoria => {
+                    ui.horizontal(|ui| {
+                        if ui.button("Cargar reglas...").clicked() {
+                            self.cargar_reglas();
+                        }
+                        if self.archivo_reglas.is_some() && ui.button("Usar categorias por defecto").clicked() {
+                            self.reglas = RuleSet::builtin();
+                            self.archivo_reglas = None;
+                        }
+                        let origen = self.archivo_reglas.as_deref().unwrap_or("categorias por defecto");
+                        ui.label(format!("({} categorias: {})", self.reglas.categories.len(), origen));
+                    });
+                }
+                Agrupacion::Fecha => {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("date_source")
+                            .selected_text(self.agrupacion_fecha.source.label())
+                            .show_ui(ui, |ui| {
+                                for source in DateSource::ALL {
+                                    ui.selectable_value(&mut self.agrupacion_fecha.source, source, source.label());
+                                }
+                            });
+                        ui.label("Plantilla:");
+                        ui.text_edit_singleline(&mut self.agrupacion_fecha.template);
+                    });
+                    ui.label("Marcadores: {year}, {month}, {day}");
+                }
+                Agrupacion::Tamano => {
+                    ui.label("Pequenos (hasta 1 MB), Medianos (hasta 100 MB), Grandes (hasta 1 GB) y Enormes");
+                }
+                Agrupacion::Patron => {
+                    ui.label("Patrones (patron=carpeta, separados por coma):");
+                    ui.text_edit_singleline(&mut self.patrones_nombre);
+                    ui.label("Ejemplo: IMG_*=Fotos, *.tmp=Temporales. Lo que no coincide se queda donde esta.");
+                }
+            }
+
+            // Detección del tipo por contenido
+            if matches!(self.agrupacion, Agrupacion::Extension | Agrupacion::Categoria) {
+                ui.horizontal(|ui| {
+                    ui.label("Tipo de archivo:");
+                    egui::ComboBox::from_id_salt("type_detection")
+                        .selected_text(self.deteccion_tipo.label())
+                        .show_ui(ui, |ui| {
+                            for detection in TypeDetection::ALL {
+                                ui.selectable_value(&mut self.deteccion_tipo, detection, detection.label());
+                            }
+                        });
+                });
+            }
+
+            // Plantilla de destino
+            ui.horizontal(|ui| {
+                ui.label("Plantilla de destino (opcional):");
+                ui.text_edit_singleline(&mut self.plantilla_destino);
+            });
+            ui.label("Marcadores: {ext} {EXT} {name} {stem} {category} {year} {month} {day} {size_bucket} {parent} {folder} {hash8}");
+
             ui.add_space(10.0);
 
             // Botones de acción
@@ -88,10 +410,16 @@ impl eframe::App for ORganizer {
 
                 if ui.button("Limpiar").clicked() {
                     self.ruta_seleccionada.clear();
+                    self.carpeta_destino.clear();
                     self.archivos_listados.clear();
                     self.resultado_organizacion = None;
+                    self.origen_resultado = None;
                     self.mostrar_resumen = false;
                     self.exclude_folders.clear();
+                    self.include_patterns.clear();
+                    self.plan_pendiente = None;
+                    self.mensaje_deshacer = None;
+                    self.duplicados = None;
                 }
             });
 
@@ -103,18 +431,29 @@ impl eframe::App for ORganizer {
                 ui.add_enabled_ui(enabled, |ui| {
                     if ui.button("✂ Cortar y Organizar").clicked() {
                         self.operation_mode = FileOperationMode::Cut;
-                        self.organizar_archivos();
+                        self.organizar_archivos(ui.ctx());
                     }
                     
                     if ui.button("📋 Copiar y Organizar").clicked() {
                         self.operation_mode = FileOperationMode::Copy;
-                        self.organizar_archivos();
+                        self.organizar_archivos(ui.ctx());
+                    }
+
+                    if ui.button("↩ Deshacer").clicked() {
+                        self.deshacer_ultima_ejecucion(ui.ctx(), false);
                     }
                 });
             });
 
+            if let Some(mensaje) = &self.mensaje_deshacer {
+                ui.add_space(5.0);
+                ui.label(mensaje);
+            }
+
             // Mostrar resultados según el estado
-            if self.mostrar_resumen {
+            if self.plan_pendiente.is_some() {
+                self.mostrar_plan(ui);
+            } else if self.mostrar_resumen {
                 self.mostrar_resumen_organizacion(ui);
             } else {
                 self.mostrar_lista_archivos(ui);
@@ -123,6 +462,46 @@ impl eframe::App for ORganizer {
     }
 }
 
+/// Duración aproximada para mostrar, p. ej. "2 min 05 s"
+fn formato_duracion(duracion: Duration) -> String {
+    let segundos = duracion.as_secs();
+    if segundos < 60 {
+        format!("{} s", segundos)
+    } else {
+        format!("{} min {:02} s", segundos / 60, segundos % 60)
+    }
+}
+
+/// Casilla y valor en MB para un límite de tamaño opcional
+fn editar_tamano(ui: &mut egui::Ui, etiqueta: &str, valor: &mut Option<u64>) {
+    const MB: u64 = 1024 * 1024;
+    let mut activo = valor.is_some();
+    ui.checkbox(&mut activo, etiqueta);
+    if activo {
+        let mut megas = valor.unwrap_or(0) / MB;
+        ui.add(egui::DragValue::new(&mut megas).suffix(" MB"));
+        *valor = Some(megas * MB);
+    } else {
+        *valor = None;
+    }
+}
+
+/// Casilla y número de días para un límite de fecha opcional
+fn editar_dias(ui: &mut egui::Ui, etiqueta: &str, valor: &mut Option<DateBound>) {
+    let mut activo = valor.is_some();
+    ui.checkbox(&mut activo, etiqueta);
+    if activo {
+        let mut dias = match valor {
+            Some(DateBound::DaysAgo(dias)) => *dias,
+            _ => 7,
+        };
+        ui.add(egui::DragValue::new(&mut dias).suffix(" dias"));
+        *valor = Some(DateBound::DaysAgo(dias));
+    } else {
+        *valor = None;
+    }
+}
+
 impl ORganizer {
     fn listar_archivos(&mut self) {
         if !self.ruta_seleccionada.is_empty() {
@@ -142,47 +521,691 @@ impl ORganizer {
         }
     }
     
-    fn organizar_archivos(&mut self) {
+    /// Separa por comas los patrones de un campo de texto
+    fn patrones(campo: &str) -> Vec<String> {
+        campo
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Calcula el plan en otro hilo; al llegar se muestra para que el usuario lo confirme
+    fn organizar_archivos(&mut self, ctx: &egui::Context) {
         if !self.ruta_seleccionada.is_empty() {
-            println!("Organizando archivos en: {} (modo: {:?})", self.ruta_seleccionada, self.operation_mode);
-
-            // Parse the excluded folders from the input field
-            let excluded_folders: Vec<String> = self.exclude_folders
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-
-            // Run the organization with exclusions and the selected operation mode
-            let result = FileOrganizerCore::organize_by_extension_with_progress_and_exclusions(
-                &self.ruta_seleccionada,
-                self.operation_mode,
-                &excluded_folders,
-                |_current, _total| {
-                    // No progress updates needed - blocking operation
+            println!("Planificando organizacion en: {} (modo: {:?})", self.ruta_seleccionada, self.operation_mode);
+
+            let strategy = match self.estrategia() {
+                Ok(strategy) => strategy,
+                Err(e) => {
+                    self.archivos_listados = vec![format!("Error al organizar: {}", e)];
+                    self.mostrar_resumen = false;
+                    return;
                 }
-            );
+            };
+
+            let options = OrganizeOptions {
+                mode: self.operation_mode,
+                output_dir: self.carpeta_destino(),
+                strategy,
+                template: self.plantilla(),
+                scan: self.opciones_escaneo(),
+                conflict_policy: self.conflict_policy,
+                verify_transfers: self.verificar_copias,
+                jobs: self.trabajos_paralelos,
+                type_detection: self.deteccion_tipo,
+                cancel: CancelToken::new(),
+            };
+
+            let ruta = self.ruta_seleccionada.clone();
+            let cancelar = options.cancel.clone();
+            self.lanzar_trabajo(ctx, "Planificando...", PathBuf::from(&ruta), Some(cancelar), move |avisar| {
+                let plan = FileOrganizerCore::plan_organize_with_progress(&ruta, &options, &|progreso| {
+                    avisar(EventoTrabajo::Progreso(progreso.clone()))
+                });
+                avisar(EventoTrabajo::Plan(Box::new(plan)));
+            });
+            self.plan_pendiente = None;
+            self.mensaje_deshacer = None;
+            self.mostrar_resumen = false;
+        } else {
+            self.archivos_listados = vec!["Por favor, selecciona una ruta primero.".to_string()];
+            self.mostrar_resumen = false;
+        }
+    }
+
+    /// Opciones de recorrido con los patrones escritos en los campos de texto
+    fn opciones_escaneo(&self) -> ScanOptions {
+        ScanOptions {
+            exclude: Self::patrones(&self.exclude_folders),
+            include: Self::patrones(&self.include_patterns),
+            filter: FileFilter {
+                name_patterns: Self::patrones(&self.filtro_nombres),
+                ..self.escaneo.filter.clone()
+            },
+            ..self.escaneo.clone()
+        }
+    }
+
+    /// Busca los duplicados en otro hilo; el informe llega por el canal del trabajo
+    fn buscar_duplicados(&mut self, ctx: &egui::Context) {
+        if self.ruta_seleccionada.is_empty() {
+            self.duplicados = None;
+            self.mensaje_duplicados = Some("Por favor, selecciona una ruta primero.".to_string());
+            return;
+        }
+
+        println!("Buscando duplicados en: {}", self.ruta_seleccionada);
+        let ruta = self.ruta_seleccionada.clone();
+        let escaneo = self.opciones_escaneo();
+        let destino = self.carpeta_destino();
+        self.lanzar_trabajo(ctx, "Buscando duplicados...", PathBuf::from(&ruta), None, move |avisar| {
+            let informe = Self::buscar_con_progreso(&ruta, &escaneo, destino.as_deref(), avisar);
+            avisar(EventoTrabajo::Duplicados(Box::new(informe)));
+        });
+    }
+
+    /// Búsqueda de duplicados que envía su avance por el canal del trabajo
+    fn buscar_con_progreso(
+        ruta: &str,
+        escaneo: &ScanOptions,
+        destino: Option<&Path>,
+        avisar: &(dyn Fn(EventoTrabajo) + Sync),
+    ) -> Result<DuplicateReport, OrganizerError> {
+        FileOrganizerCore::find_duplicates_with_progress(ruta, escaneo, destino, &|progreso| {
+            avisar(EventoTrabajo::Progreso(progreso.clone()))
+        })
+    }
+
+    /// Regla elegida, con la carpeta escrita en el campo de texto
+    fn regla_conservar(&self) -> KeepRule {
+        match self.regla_conservar {
+            KeepRule::InFolder(_) => KeepRule::InFolder(PathBuf::from(self.carpeta_preferida.trim())),
+            ref regla => regla.clone(),
+        }
+    }
 
-            match result {
+    /// Aplica en otro hilo la acción elegida a las copias sobrantes y vuelve a buscar
+    fn resolver_duplicados(&mut self, ctx: &egui::Context) {
+        let informe = match self.duplicados.take() {
+            Some(Ok(informe)) => informe,
+            otro => {
+                self.duplicados = otro;
+                return;
+            }
+        };
+
+        let ruta = self.ruta_seleccionada.clone();
+        let escaneo = self.opciones_escaneo();
+        let destino = self.carpeta_destino();
+        let regla = self.regla_conservar();
+        let accion = self.accion_duplicados;
+        let raiz = informe.root.clone();
+        self.lanzar_trabajo(ctx, "Resolviendo duplicados...", raiz, None, move |avisar| {
+            let resultado = FileOrganizerCore::resolve_duplicates(&informe, &regla, accion, destino.as_deref());
+            avisar(EventoTrabajo::Resueltos(match resultado {
                 Ok(resultado) => {
-                    println!("Organizacion completada exitosamente");
-                    self.resultado_organizacion = Some(resultado);
-                    self.mostrar_resumen = true;
+                    let mut mensaje = resultado.summary;
+                    for error in resultado.errors.unwrap_or_default() {
+                        mensaje.push_str(&format!("- {}\n", error));
+                    }
+                    mensaje
+                }
+                Err(e) => format!("Error: {}", e),
+            }));
 
-                    // Actualizar lista de archivos después de organizar
-                    self.listar_archivos();
+            let informe = Self::buscar_con_progreso(&ruta, &escaneo, destino.as_deref(), avisar);
+            avisar(EventoTrabajo::Duplicados(Box::new(informe)));
+        });
+    }
+
+    /// Estrategia correspondiente al criterio de agrupación elegido
+    fn estrategia(&self) -> Result<Arc<dyn OrganizeStrategy>, String> {
+        let strategy: Arc<dyn OrganizeStrategy> = match self.agrupacion {
+            Agrupacion::Extension => Arc::new(ExtensionStrategy),
+            Agrupacion::Categoria => Arc::new(CategoryStrategy::new(self.reglas.clone())),
+            Agrupacion::Fecha => Arc::new(DateStrategy::new(self.agrupacion_fecha.clone())),
+            Agrupacion::Tamano => Arc::new(SizeBucketStrategy::default()),
+            Agrupacion::Patron => {
+                let patterns = self.patrones_nombre
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(NamePattern::parse)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Arc::new(NamePatternStrategy { patterns, fallback: None })
+            }
+        };
+        Ok(strategy)
+    }
+
+    /// Carpeta de destino elegida, o `None` para usar `Organizer/`
+    fn carpeta_destino(&self) -> Option<PathBuf> {
+        let carpeta = self.carpeta_destino.trim();
+        (!carpeta.is_empty()).then(|| PathBuf::from(carpeta))
+    }
+
+    /// Plantilla de destino escrita por el usuario, con sus categorías y su origen de fecha
+    fn plantilla(&self) -> Option<PathTemplate> {
+        let plantilla = self.plantilla_destino.trim();
+        if plantilla.is_empty() {
+            return None;
+        }
+
+        let mut template = PathTemplate::new(plantilla);
+        template.rules = self.reglas.clone();
+        template.date_source = self.agrupacion_fecha.source;
+        Some(template)
+    }
+
+    /// Lanza `tarea` en otro hilo; el avance y el resultado llegan por un canal
+    /// que lee `recibir_trabajo`
+    fn lanzar_trabajo<F>(
+        &mut self,
+        ctx: &egui::Context,
+        tarea: &'static str,
+        raiz: PathBuf,
+        cancelar: Option<CancelToken>,
+        trabajo: F,
+    ) where
+        F: FnOnce(&(dyn Fn(EventoTrabajo) + Sync)) + Send + 'static,
+    {
+        let (emisor, receptor) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let avisar = |evento| {
+                let _ = emisor.send(evento);
+                ctx.request_repaint();
+            };
+            trabajo(&avisar);
+        });
+
+        self.trabajo = Some(Trabajo { receptor, tarea, progreso: None, raiz, cancelar });
+    }
+
+    /// Ejecuta en otro hilo el plan que el usuario acaba de confirmar
+    fn confirmar_plan(&mut self, ctx: &egui::Context) {
+        let Some(plan) = self.plan_pendiente.take() else {
+            return;
+        };
+
+        let decisiones = std::mem::take(&mut self.decisiones_conflicto);
+        let raiz = plan.root.clone();
+        let cancelar = plan.cancel.clone();
+        self.lanzar_trabajo(ctx, "Organizando...", raiz, Some(cancelar), move |avisar| {
+            let result = FileOrganizerCore::execute_plan_with_resolver(
+                &plan,
+                &|progreso| avisar(EventoTrabajo::Progreso(progreso.clone())),
+                |operation| decisiones.get(&operation.source).copied().unwrap_or(ConflictPolicy::Skip),
+            );
+            avisar(EventoTrabajo::Terminado(Box::new(result), plan.root.clone(), plan.mode));
+        });
+        self.mensaje_deshacer = None;
+    }
+
+    /// Recoge lo que haya enviado el hilo del trabajo en curso
+    fn recibir_trabajo(&mut self) {
+        let Some(trabajo) = &mut self.trabajo else {
+            return;
+        };
+
+        let evento = loop {
+            match trabajo.receptor.try_recv() {
+                Ok(EventoTrabajo::Progreso(progreso)) => trabajo.progreso = Some(progreso),
+                Ok(EventoTrabajo::Resueltos(mensaje)) => {
+                    self.mensaje_duplicados = Some(mensaje);
+                    trabajo.tarea = "Buscando duplicados...";
+                    trabajo.progreso = None;
                 }
-                Err(e) => {
-                    self.archivos_listados = vec![format!("Error al organizar: {}", e)];
+                Ok(evento) => break evento,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.trabajo = None;
+                    self.archivos_listados = vec!["Error: la operacion se interrumpio".to_string()];
                     self.mostrar_resumen = false;
+                    return;
                 }
             }
+        };
+        // Tras deshacer desde la pestaña de duplicados el mismo hilo vuelve a buscar
+        if let EventoTrabajo::Deshecho(_, true) = &evento {
+            trabajo.tarea = "Buscando duplicados...";
+            trabajo.progreso = None;
+            trabajo.cancelar = None;
         } else {
-            self.archivos_listados = vec!["Por favor, selecciona una ruta primero.".to_string()];
-            self.mostrar_resumen = false;
+            self.trabajo = None;
+        }
+
+        let (result, raiz, modo) = match evento {
+            EventoTrabajo::Terminado(result, raiz, modo) => (result, raiz, modo),
+            EventoTrabajo::Plan(plan) => {
+                match *plan {
+                    Ok(plan) if plan.cancelled => {
+                        self.archivos_listados = vec!["Organizacion cancelada: no se ha movido nada".to_string()];
+                    }
+                    Ok(plan) => {
+                        self.plan_pendiente = Some(plan);
+                        self.decisiones_conflicto.clear();
+                    }
+                    Err(e) => {
+                        self.archivos_listados = vec![format!("Error al organizar: {}", e)];
+                    }
+                }
+                return;
+            }
+            EventoTrabajo::Duplicados(informe) => {
+                self.duplicados = Some(*informe);
+                return;
+            }
+            EventoTrabajo::Deshecho(resultado, _) => {
+                self.aplicar_deshacer(*resultado);
+                return;
+            }
+            EventoTrabajo::Progreso(_) | EventoTrabajo::Resueltos(_) => return,
+        };
+
+        match *result {
+            Ok(resultado) => {
+                println!("Organizacion completada exitosamente");
+                self.resultado_organizacion = Some(resultado);
+                self.origen_resultado = Some((raiz, modo));
+                self.mostrar_resumen = true;
+                self.mensaje_informe = None;
+
+                // Actualizar lista de archivos después de organizar
+                self.listar_archivos();
+            }
+            Err(e) => {
+                self.archivos_listados = vec![format!("Error al organizar: {}", e)];
+                self.mostrar_resumen = false;
+            }
         }
     }
-    
+
+    /// Carga las categorías desde un archivo TOML o JSON elegido por el usuario
+    fn cargar_reglas(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("Reglas", &["toml", "json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match RuleSet::load(&path) {
+            Ok(reglas) => {
+                self.reglas = reglas;
+                self.archivo_reglas = Some(path.to_string_lossy().to_string());
+            }
+            Err(e) => {
+                self.archivos_listados = vec![format!("Error al cargar reglas: {}", e)];
+            }
+        }
+    }
+
+    /// Guarda el informe de la última ejecución; el formato sale de la extensión elegida
+    fn exportar_informe(&mut self) {
+        let (Some(resultado), Some((raiz, modo))) = (&self.resultado_organizacion, &self.origen_resultado) else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .set_file_name("informe.json")
+            .add_filter("JSON", &["json"])
+            .add_filter("CSV", &["csv"])
+            .add_filter("Markdown", &["md"])
+            .add_filter("HTML", &["html"])
+            .save_file()
+        else {
+            return;
+        };
+
+        let formato = ReportFormat::from_path(&path).unwrap_or_default();
+        let informe = RunReport::new(raiz, *modo, resultado);
+        self.mensaje_informe = Some(match informe.write(&path, formato) {
+            Ok(()) => format!("Informe guardado en {}", path.display()),
+            Err(e) => format!("Error al exportar el informe: {}", e),
+        });
+    }
+
+    /// Revierte en otro hilo la última ejecución registrada en la carpeta
+    /// seleccionada; con `buscar_despues` vuelve a buscar duplicados al terminar
+    fn deshacer_ultima_ejecucion(&mut self, ctx: &egui::Context, buscar_despues: bool) {
+        println!("Deshaciendo ultima ejecucion en: {}", self.ruta_seleccionada);
+
+        let ruta = self.ruta_seleccionada.clone();
+        let escaneo = self.opciones_escaneo();
+        let destino = self.carpeta_destino();
+        let cancelar = CancelToken::new();
+        let token = cancelar.clone();
+        self.lanzar_trabajo(ctx, "Deshaciendo...", PathBuf::from(&ruta), Some(cancelar), move |avisar| {
+            let resultado = FileOrganizerCore::undo_with_progress(
+                &ruta,
+                destino.as_deref(),
+                None,
+                &|progreso| avisar(EventoTrabajo::Progreso(progreso.clone())),
+                &token,
+            );
+            avisar(EventoTrabajo::Deshecho(Box::new(resultado), buscar_despues));
+
+            if buscar_despues {
+                let informe = Self::buscar_con_progreso(&ruta, &escaneo, destino.as_deref(), avisar);
+                avisar(EventoTrabajo::Duplicados(Box::new(informe)));
+            }
+        });
+        self.mensaje_deshacer = None;
+    }
+
+    /// Muestra el resultado de deshacer y refresca la lista de archivos
+    fn aplicar_deshacer(&mut self, resultado: Result<UndoResult, OrganizerError>) {
+        match resultado {
+            Ok(resultado) => {
+                let mut mensaje = resultado.summary;
+                if let Some(errors) = resultado.errors {
+                    for error in errors {
+                        mensaje.push_str(&format!("- {}\n", error));
+                    }
+                }
+                self.mensaje_deshacer = Some(mensaje);
+                self.resultado_organizacion = None;
+                self.origen_resultado = None;
+                self.mostrar_resumen = false;
+                self.plan_pendiente = None;
+
+                self.listar_archivos();
+            }
+            Err(e) => {
+                self.mensaje_deshacer = Some(format!("Error al deshacer: {}", e));
+            }
+        }
+    }
+
+    fn mostrar_plan(&mut self, ui: &mut egui::Ui) {
+        let Some(plan) = &self.plan_pendiente else {
+            return;
+        };
+
+        ui.add_space(10.0);
+        ui.separator();
+        ui.heading("Vista previa de la organizacion");
+
+        let mut confirmar = false;
+        let mut cancelar = false;
+
+        if plan.is_empty() {
+            ui.label("No hay archivos para organizar.");
+        } else {
+            let accion = match plan.mode {
+                FileOperationMode::Cut => "Mover",
+                FileOperationMode::Copy => "Copiar",
+            };
+
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Operaciones planificadas:").strong());
+                ui.label(format!("{}", plan.operations.len()));
+            });
+
+            let conflictos = plan.conflict_count();
+            if conflictos > 0 {
+                ui.label(
+                    RichText::new(format!("{} archivo(s) con conflictos", conflictos))
+                        .color(egui::Color32::YELLOW),
+                );
+            }
+            if !plan.type_mismatches.is_empty() {
+                ui.label(
+                    RichText::new(format!(
+                        "{} archivo(s) con una extension que no corresponde a su contenido",
+                        plan.type_mismatches.len()
+                    ))
+                    .color(egui::Color32::YELLOW),
+                );
+            }
+
+            if !plan.skipped.is_empty() {
+                ui.label(format!("{} archivo(s) se quedan donde estan", plan.skipped.len()));
+            }
+            for (archivo, cantidad) in count_by_ignore_file(&plan.ignored) {
+                let archivo = archivo.strip_prefix(&plan.root).unwrap_or(archivo);
+                ui.label(format!("{} elemento(s) ignorados por {}", cantidad, archivo.display()));
+            }
+            if !plan.filtered.is_empty() {
+                ui.label(format!("{} archivo(s) descartados por los filtros", plan.filtered.len()));
+            }
+            for ruta in &plan.unreadable {
+                ui.label(RichText::new(format!("No se pudo leer: {}", ruta.error)).color(egui::Color32::YELLOW));
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for operation in &plan.operations {
+                        let origen = operation.source.strip_prefix(&plan.root).unwrap_or(&operation.source);
+                        let destino = operation.destination.strip_prefix(&plan.root).unwrap_or(&operation.destination);
+                        ui.label(format!(
+                            "{} {} -> {} ({})",
+                            accion,
+                            origen.display(),
+                            destino.display(),
+                            operation.reason
+                        ));
+                        for conflict in &operation.conflicts {
+                            ui.label(RichText::new(format!("    conflicto: {}", conflict)).color(egui::Color32::YELLOW));
+                        }
+
+                        // Con `Preguntar` el usuario decide aquí cada conflicto antes de confirmar
+                        if plan.conflict_policy == ConflictPolicy::Ask && operation.has_conflicts() {
+                            let decision = self.decisiones_conflicto
+                                .entry(operation.source.clone())
+                                .or_insert(ConflictPolicy::Skip);
+                            ui.horizontal(|ui| {
+                                ui.label("    resolver:");
+                                egui::ComboBox::from_id_salt(&operation.source)
+                                    .selected_text(decision.label())
+                                    .show_ui(ui, |ui| {
+                                        for policy in ConflictPolicy::ALL {
+                                            if policy != ConflictPolicy::Ask {
+                                                ui.selectable_value(decision, policy, policy.label());
+                                            }
+                                        }
+                                    });
+                            });
+                        }
+                    }
+                });
+        }
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            if !plan.is_empty() && ui.button("Confirmar").clicked() {
+                confirmar = true;
+            }
+            if ui.button("Cancelar").clicked() {
+                cancelar = true;
+            }
+        });
+
+        if confirmar {
+            self.confirmar_plan(ui.ctx());
+        } else if cancelar {
+            self.plan_pendiente = None;
+        }
+    }
+
+    /// Barra de progreso de la organización en curso
+    fn mostrar_progreso(&self, ui: &mut egui::Ui) {
+        let Some(trabajo) = &self.trabajo else {
+            return;
+        };
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.heading(trabajo.tarea);
+            match &trabajo.cancelar {
+                Some(cancelar) if cancelar.is_cancelled() => {
+                    ui.label(RichText::new("Cancelando...").color(egui::Color32::YELLOW));
+                }
+                // Se detiene entre archivos o entre bloques de la copia en curso
+                Some(cancelar) if ui.button("Cancelar").clicked() => cancelar.cancel(),
+                _ => {}
+            }
+        });
+
+        let Some(progreso) = &trabajo.progreso else {
+            ui.add(egui::ProgressBar::new(0.0).animate(true));
+            return;
+        };
+
+        let restante = progreso
+            .eta()
+            .map(|eta| format!(", quedan ~{}", formato_duracion(eta)))
+            .unwrap_or_default();
+        ui.add(
+            egui::ProgressBar::new(progreso.fraction())
+                .animate(true)
+                .text(format!(
+                    "{} de {}{} archivos ({:.0}%){}",
+                    progreso.current,
+                    if progreso.estimated { "~" } else { "" },
+                    progreso.total,
+                    progreso.fraction() * 100.0,
+                    restante
+                )),
+        );
+
+        let archivo = progreso.file.strip_prefix(&trabajo.raiz).unwrap_or(&progreso.file);
+        ui.label(format!("Archivo: {}", archivo.display()));
+        ui.label(format!(
+            "{} de {}",
+            format_size(progreso.bytes_done),
+            format_size(progreso.bytes_total)
+        ));
+        ui.add_space(10.0);
+        ui.separator();
+    }
+
+    fn mostrar_duplicados(&mut self, ui: &mut egui::Ui) {
+        ui.add_enabled_ui(!self.ruta_seleccionada.is_empty(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("🔍 Buscar duplicados").clicked() {
+                    self.mensaje_duplicados = None;
+                    self.buscar_duplicados(ui.ctx());
+                }
+                if ui.button("↩ Deshacer").clicked() {
+                    self.deshacer_ultima_ejecucion(ui.ctx(), true);
+                }
+            });
+        });
+
+        if let Some(mensaje) = &self.mensaje_deshacer {
+            ui.label(mensaje);
+        }
+        if let Some(mensaje) = &self.mensaje_duplicados {
+            ui.label(mensaje);
+        }
+
+        let informe = match &self.duplicados {
+            None => return,
+            Some(Err(e)) => {
+                ui.add_space(10.0);
+                ui.label(RichText::new(format!("Error: {}", e)).color(egui::Color32::RED));
+                return;
+            }
+            Some(Ok(informe)) => informe,
+        };
+
+        ui.add_space(10.0);
+        ui.separator();
+
+        let mut resolver = false;
+        if informe.groups.is_empty() {
+            ui.label(format!("No hay duplicados ({} archivos revisados).", informe.files_scanned));
+        } else {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Grupos de duplicados:").strong());
+                ui.label(format!(
+                    "{} ({} copias sobrantes de {} archivos revisados)",
+                    informe.groups.len(),
+                    informe.duplicate_files(),
+                    informe.files_scanned
+                ));
+            });
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Espacio recuperable:").strong());
+                ui.label(format_size(informe.reclaimable_bytes));
+            });
+
+            // Qué copia se conserva y qué se hace con el resto
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label("Conservar:");
+                egui::ComboBox::from_id_salt("regla_conservar")
+                    .selected_text(self.regla_conservar.label())
+                    .show_ui(ui, |ui| {
+                        for regla in [
+                            KeepRule::ShortestPath,
+                            KeepRule::Oldest,
+                            KeepRule::Newest,
+                            KeepRule::InFolder(PathBuf::new()),
+                        ] {
+                            let etiqueta = regla.label();
+                            ui.selectable_value(&mut self.regla_conservar, regla, etiqueta);
+                        }
+                    });
+                if matches!(self.regla_conservar, KeepRule::InFolder(_)) {
+                    ui.add(egui::TextEdit::singleline(&mut self.carpeta_preferida).hint_text("Originales/"));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Con las demas copias:");
+                egui::ComboBox::from_id_salt("accion_duplicados")
+                    .selected_text(self.accion_duplicados.label())
+                    .show_ui(ui, |ui| {
+                        for accion in DuplicateAction::ALL {
+                            ui.selectable_value(&mut self.accion_duplicados, accion, accion.label());
+                        }
+                    });
+                resolver = ui.button("Resolver duplicados").clicked();
+            });
+
+            let regla = self.regla_conservar().within(&informe.root);
+            egui::ScrollArea::vertical()
+                .id_salt("duplicados")
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for grupo in &informe.groups {
+                        let conservar = grupo.keeper(&regla);
+                        let titulo = format!(
+                            "{} copias de {} ({})",
+                            grupo.files.len(),
+                            format_size(grupo.size),
+                            &grupo.hash[..12]
+                        );
+                        egui::CollapsingHeader::new(titulo).id_salt(&grupo.hash).show(ui, |ui| {
+                            for archivo in &grupo.files {
+                                let ruta = archivo.strip_prefix(&informe.root).unwrap_or(archivo);
+                                if archivo.as_path() == conservar {
+                                    ui.label(RichText::new(format!("- {} (se conserva)", ruta.display())).strong());
+                                } else {
+                                    ui.label(format!("- {}", ruta.display()));
+                                }
+                            }
+                        });
+                    }
+                });
+        }
+
+        if !informe.errors.is_empty() {
+            ui.add_space(10.0);
+            ui.label(RichText::new("Archivos que no se pudieron leer:").color(egui::Color32::RED));
+            for error in &informe.errors {
+                ui.label(format!("- {}", error));
+            }
+        }
+
+        if resolver {
+            self.resolver_duplicados(ui.ctx());
+        }
+    }
+
     fn mostrar_lista_archivos(&mut self, ui: &mut egui::Ui) {
         if !self.archivos_listados.is_empty() {
             ui.add_space(10.0);
@@ -201,11 +1224,15 @@ impl ORganizer {
                     }
                 });
             
-            // Mostrar información sobre la carpeta Organizer si existe
-            let organizer_path = format!("{}/Organizer", self.ruta_seleccionada);
-            if std::path::Path::new(&organizer_path).exists() {
+            // Mostrar información sobre la carpeta de destino si ya existe
+            let destino = self.carpeta_destino();
+            let organizer_path = FileOrganizerCore::output_root(Path::new(&self.ruta_seleccionada), destino.as_deref());
+            if organizer_path.exists() {
                 ui.add_space(10.0);
-                ui.label(RichText::new("Nota: Ya existe una carpeta 'Organizer' en esta ubicacion.").color(egui::Color32::YELLOW));
+                ui.label(
+                    RichText::new(format!("Nota: Ya existe la carpeta de destino '{}'.", organizer_path.display()))
+                        .color(egui::Color32::YELLOW),
+                );
             }
         }
     }
@@ -216,9 +1243,16 @@ impl ORganizer {
             ui.separator();
 
             ui.heading("Resumen de Organizacion");
+            if resultado.cancelled {
+                ui.label(
+                    RichText::new("Organizacion cancelada: solo se procesaron los archivos de la lista.")
+                        .color(egui::Color32::YELLOW),
+                );
+            }
 
-            // Show operation mode in the summary
-            let operation_text = match self.operation_mode {
+            // Modo con el que se ejecutó el plan, no el que esté elegido ahora
+            let modo = self.origen_resultado.as_ref().map_or(self.operation_mode, |(_, modo)| *modo);
+            let operation_text = match modo {
                 FileOperationMode::Cut => "Archivos movidos:",
                 FileOperationMode::Copy => "Archivos copiados:",
             };
@@ -261,26 +1295,103 @@ impl ORganizer {
                     ui.label(&resultado.summary);
                 });
             
-            // Mostrar errores si los hay
-            if let Some(errors) = &resultado.errors {
-                if !errors.is_empty() {
-                    ui.add_space(10.0);
-                    ui.label(RichText::new("Errores encontrados:").color(egui::Color32::RED));
-                    
+            // Mostrar cómo se resolvió cada conflicto
+            if !resultado.conflicts.is_empty() {
+                ui.add_space(10.0);
+                ui.label(RichText::new("Conflictos:").color(egui::Color32::YELLOW));
+
+                egui::ScrollArea::vertical()
+                    .id_salt("conflictos")
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for conflict in &resultado.conflicts {
+                            ui.label(format!("- {}: {}", conflict.destination.display(), conflict.resolution));
+                        }
+                    });
+            }
+
+            // Mostrar qué dejó fuera cada `.organizerignore`
+            if !resultado.ignored.is_empty() {
+                ui.add_space(10.0);
+                ui.label(RichText::new("Ignorados por .organizerignore:").strong());
+                for (archivo, cantidad) in count_by_ignore_file(&resultado.ignored) {
+                    ui.label(format!("- {}: {} elemento(s)", archivo.display(), cantidad));
+                }
+            }
+
+            // Mostrar archivos con la extensión equivocada
+            if !resultado.type_mismatches.is_empty() {
+                ui.add_space(10.0);
+                ui.label(RichText::new("Extension distinta al contenido:").color(egui::Color32::YELLOW));
+
+                egui::ScrollArea::vertical()
+                    .id_salt("tipos")
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for mismatch in &resultado.type_mismatches {
+                            ui.label(format!(
+                                "- {}: .{} pero parece .{}",
+                                mismatch.path.display(),
+                                mismatch.extension,
+                                mismatch.detected.extension
+                            ));
+                        }
+                    });
+            }
+
+            // Qué pasó con cada archivo
+            if !resultado.outcomes.is_empty() {
+                ui.add_space(10.0);
+                egui::CollapsingHeader::new(format!("Detalle por archivo ({})", resultado.outcomes.len())).show(ui, |ui| {
                     egui::ScrollArea::vertical()
-                        .max_height(100.0)
+                        .id_salt("detalle")
+                        .max_height(150.0)
                         .show(ui, |ui| {
-                            for error in errors {
-                                ui.label(format!("- {}", error));
+                            for outcome in &resultado.outcomes {
+                                ui.label(format!(
+                                    "- [{}] {} -> {} ({}, {} ms)",
+                                    outcome.status,
+                                    outcome.source.display(),
+                                    outcome.destination.display(),
+                                    format_size(outcome.bytes),
+                                    outcome.duration.as_millis()
+                                ));
                             }
                         });
-                }
+                });
+            }
+
+            // Mostrar errores si los hay
+            let errors: Vec<_> = resultado.all_errors().collect();
+            if !errors.is_empty() {
+                ui.add_space(10.0);
+                ui.label(RichText::new("Errores encontrados:").color(egui::Color32::RED));
+
+                egui::ScrollArea::vertical()
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for error in errors {
+                            ui.label(format!("- {}", error));
+                        }
+                    });
             }
             
-            // Botón para volver a la lista
+            if let Some(mensaje) = &self.mensaje_informe {
+                ui.add_space(10.0);
+                ui.label(mensaje);
+            }
+
+            // Botones para volver a la lista y exportar el informe
             ui.add_space(10.0);
-            if ui.button("Volver a la lista de archivos").clicked() {
-                self.mostrar_resumen = false;
+            let mut exportar = false;
+            ui.horizontal(|ui| {
+                if ui.button("Volver a la lista de archivos").clicked() {
+                    self.mostrar_resumen = false;
+                }
+                exportar = ui.button("Exportar informe").clicked();
+            });
+            if exportar {
+                self.exportar_informe();
             }
         }
     }